@@ -0,0 +1,2 @@
+-- set on every login through authn, api tokens keep their own last_used
+ALTER TABLE users ADD COLUMN last_login text;
@@ -0,0 +1,10 @@
+CREATE TABLE api_tokens (
+	id integer PRIMARY KEY,
+	user_id integer NOT NULL REFERENCES users(id),
+	name text NOT NULL,
+	-- hex encoded sha256 of the token, the token itself is never stored
+	hash text NOT NULL UNIQUE,
+	created text NOT NULL DEFAULT (datetime('now', 'utc')),
+	last_used text,
+	revoked text
+);
@@ -0,0 +1,13 @@
+-- the url links are deduped on, filled in when the server starts since it
+-- depends on the server's config. it's null for links which collided with an
+-- older one.
+ALTER TABLE links ADD COLUMN canonical text;
+
+CREATE UNIQUE INDEX links_user_canonical ON links (user_id, canonical);
+
+-- the canonical config links.canonical was computed with, so links are only
+-- canonicalized again when it changes
+CREATE TABLE canonical_config (
+	id integer PRIMARY KEY CHECK (id = 0),
+	config text NOT NULL
+);
@@ -0,0 +1,22 @@
+-- named sets of links which can be shared. public collections are listed on
+-- the user's public page, unlisted ones are only found through their slug.
+CREATE TABLE collections (
+	id integer PRIMARY KEY,
+	user_id integer NOT NULL REFERENCES users(id),
+	name text NOT NULL,
+	visibility text NOT NULL DEFAULT 'private'
+		CHECK (visibility IN ('public', 'unlisted', 'private')),
+	-- random, so unlisted collections can't be guessed
+	slug text NOT NULL UNIQUE,
+	created text NOT NULL DEFAULT (datetime('now', 'utc')),
+	UNIQUE (user_id, name)
+);
+
+CREATE TABLE collection_links (
+	collection_id integer NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
+	link_id integer NOT NULL REFERENCES links(id) ON DELETE CASCADE,
+	added text NOT NULL DEFAULT (datetime('now', 'utc')),
+	PRIMARY KEY (collection_id, link_id)
+);
+
+CREATE INDEX collection_links_link ON collection_links (link_id);
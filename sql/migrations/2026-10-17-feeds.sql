@@ -0,0 +1,61 @@
+-- lets feed readers, which can't log in, follow an account's feeds. it's
+-- kept as is rather than hashed so the feed urls can be shown again.
+ALTER TABLE users ADD COLUMN feed_token text;
+
+CREATE UNIQUE INDEX users_feed_token ON users (feed_token);
+
+-- when the account's links, their tags or its collections last changed, used
+-- as the last-modified date of its feeds. null until the account's first
+-- link.
+ALTER TABLE users ADD COLUMN links_changed text;
+
+-- what changed before this is unknown
+UPDATE users SET links_changed = datetime('now', 'utc')
+WHERE id IN (SELECT user_id FROM links);
+
+CREATE TRIGGER links_changed_insert AFTER INSERT ON links BEGIN
+	UPDATE users SET links_changed = datetime('now', 'utc')
+	WHERE id = new.user_id;
+END;
+
+CREATE TRIGGER links_changed_update AFTER UPDATE ON links BEGIN
+	UPDATE users SET links_changed = datetime('now', 'utc')
+	WHERE id IN (old.user_id, new.user_id);
+END;
+
+CREATE TRIGGER links_changed_delete AFTER DELETE ON links BEGIN
+	UPDATE users SET links_changed = datetime('now', 'utc')
+	WHERE id = old.user_id;
+END;
+
+CREATE TRIGGER link_tags_changed_insert AFTER INSERT ON link_tags BEGIN
+	UPDATE users SET links_changed = datetime('now', 'utc')
+	WHERE id = (SELECT user_id FROM links WHERE id = new.link_id);
+END;
+
+CREATE TRIGGER link_tags_changed_delete AFTER DELETE ON link_tags BEGIN
+	UPDATE users SET links_changed = datetime('now', 'utc')
+	WHERE id = (SELECT user_id FROM links WHERE id = old.link_id);
+END;
+
+CREATE TRIGGER collection_links_changed_insert
+AFTER INSERT ON collection_links BEGIN
+	UPDATE users SET links_changed = datetime('now', 'utc')
+	WHERE id = (SELECT user_id FROM collections WHERE id = new.collection_id);
+END;
+
+CREATE TRIGGER collection_links_changed_delete
+AFTER DELETE ON collection_links BEGIN
+	UPDATE users SET links_changed = datetime('now', 'utc')
+	WHERE id = (SELECT user_id FROM collections WHERE id = old.collection_id);
+END;
+
+CREATE TRIGGER collections_changed_insert AFTER INSERT ON collections BEGIN
+	UPDATE users SET links_changed = datetime('now', 'utc')
+	WHERE id = new.user_id;
+END;
+
+CREATE TRIGGER collections_changed_update AFTER UPDATE ON collections BEGIN
+	UPDATE users SET links_changed = datetime('now', 'utc')
+	WHERE id = new.user_id;
+END;
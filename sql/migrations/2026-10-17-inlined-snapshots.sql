@@ -0,0 +1,15 @@
+-- a self-contained version of html snapshots with assets inlined and scripts
+-- removed
+ALTER TABLE snapshots ADD COLUMN inlined_digest text REFERENCES blobs(digest);
+
+DROP TRIGGER snapshots_delete;
+
+CREATE TRIGGER snapshots_delete AFTER DELETE ON snapshots BEGIN
+	DELETE FROM blobs
+	WHERE digest IN (old.digest, old.inlined_digest)
+	AND NOT EXISTS (
+		SELECT 1 FROM snapshots
+		WHERE snapshots.digest = blobs.digest
+		OR snapshots.inlined_digest = blobs.digest
+	);
+END;
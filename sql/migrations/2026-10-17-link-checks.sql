@@ -0,0 +1,24 @@
+-- the latest health check of each link
+CREATE TABLE link_checks (
+	link_id integer PRIMARY KEY REFERENCES links(id) ON DELETE CASCADE,
+	checked text NOT NULL DEFAULT (datetime('now', 'utc')),
+	status integer,
+	-- where the link redirects to, if it's somewhere else
+	location text,
+	-- set when there was no response
+	error text,
+	-- consecutive failed checks, used to back off
+	failures integer NOT NULL DEFAULT 0,
+	next_check text NOT NULL,
+	health text GENERATED ALWAYS AS (
+		CASE
+			WHEN error IS NOT NULL OR status IS NULL OR status >= 400
+				THEN 'broken'
+			WHEN location IS NOT NULL THEN 'moved'
+			ELSE 'ok'
+		END
+	) VIRTUAL
+);
+
+CREATE INDEX link_checks_next_check ON link_checks (next_check);
+CREATE INDEX link_checks_health ON link_checks (health);
@@ -0,0 +1,3 @@
+ALTER TABLE links ADD COLUMN title text;
+ALTER TABLE links ADD COLUMN description text;
+ALTER TABLE links ADD COLUMN notes text;
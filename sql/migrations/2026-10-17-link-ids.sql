@@ -0,0 +1,15 @@
+CREATE TABLE links_new (
+	id integer PRIMARY KEY,
+	user_id integer NOT NULL REFERENCES users(id),
+	url text NOT NULL,
+	created text NOT NULL DEFAULT (datetime('now', 'utc')),
+	deleted text,
+	UNIQUE (user_id, url)
+);
+
+INSERT INTO links_new (user_id, url, created, deleted)
+SELECT user_id, url, created, deleted FROM links ORDER BY created;
+
+DROP TABLE links;
+
+ALTER TABLE links_new RENAME TO links;
@@ -0,0 +1,10 @@
+-- metadata extracted from the page when it's archived, kept apart from the
+-- title and description the user wrote
+ALTER TABLE links ADD COLUMN page_title text;
+ALTER TABLE links ADD COLUMN page_description text;
+ALTER TABLE links ADD COLUMN image text;
+ALTER TABLE links ADD COLUMN site_name text;
+ALTER TABLE links ADD COLUMN canonical_url text;
+ALTER TABLE links ADD COLUMN author text;
+ALTER TABLE links ADD COLUMN published text;
+ALTER TABLE links ADD COLUMN favicon text;
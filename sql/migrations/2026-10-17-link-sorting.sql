@@ -0,0 +1,12 @@
+-- urls are stored normalized, so the host is whatever is between the scheme
+-- and the first slash of the path
+ALTER TABLE links ADD COLUMN domain text GENERATED ALWAYS AS (
+	substr(
+		substr(url, instr(url, '://') + 3),
+		1,
+		instr(substr(url, instr(url, '://') + 3) || '/', '/') - 1
+	)
+) VIRTUAL;
+
+CREATE INDEX links_user_created ON links (user_id, created, url);
+CREATE INDEX links_user_domain ON links (user_id, domain, url);
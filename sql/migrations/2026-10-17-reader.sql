@@ -0,0 +1,24 @@
+-- reader mode versions of html snapshots, and their text which is also what
+-- links_fts.text is filled with
+ALTER TABLE snapshots ADD COLUMN reader_digest text REFERENCES blobs(digest);
+ALTER TABLE snapshots ADD COLUMN text_digest text REFERENCES blobs(digest);
+ALTER TABLE snapshots ADD COLUMN words integer;
+
+DROP TRIGGER snapshots_delete;
+
+CREATE TRIGGER snapshots_delete AFTER DELETE ON snapshots BEGIN
+	DELETE FROM blobs
+	WHERE digest IN (
+		old.digest,
+		old.inlined_digest,
+		old.reader_digest,
+		old.text_digest
+	)
+	AND NOT EXISTS (
+		SELECT 1 FROM snapshots
+		WHERE snapshots.digest = blobs.digest
+		OR snapshots.inlined_digest = blobs.digest
+		OR snapshots.reader_digest = blobs.digest
+		OR snapshots.text_digest = blobs.digest
+	);
+END;
@@ -0,0 +1,3 @@
+-- set when the user asks for a link to be archived again, cleared once the
+-- archiver has fetched it
+ALTER TABLE links ADD COLUMN archive_requested text;
@@ -0,0 +1,26 @@
+-- the text column holds the archived page text, it's filled in by the
+-- archiver rather than kept in sync by the triggers
+CREATE VIRTUAL TABLE links_fts USING fts5(url, title, description, notes, text);
+
+INSERT INTO links_fts (rowid, url, title, description, notes)
+SELECT id, url, title, description, notes FROM links;
+
+CREATE TRIGGER links_fts_insert AFTER INSERT ON links BEGIN
+	INSERT INTO links_fts (rowid, url, title, description, notes)
+	VALUES (new.id, new.url, new.title, new.description, new.notes);
+END;
+
+CREATE TRIGGER links_fts_delete AFTER DELETE ON links BEGIN
+	DELETE FROM links_fts WHERE rowid = old.id;
+END;
+
+CREATE TRIGGER links_fts_update AFTER UPDATE OF url, title, description, notes
+ON links BEGIN
+	UPDATE links_fts
+	SET
+		url = new.url,
+		title = new.title,
+		description = new.description,
+		notes = new.notes
+	WHERE rowid = new.id;
+END;
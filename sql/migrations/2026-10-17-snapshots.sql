@@ -0,0 +1,30 @@
+-- content addressed storage for fetched bodies, identical pages are only
+-- stored once
+CREATE TABLE blobs (
+	-- "sha256:" followed by the hex encoded digest of data
+	digest text PRIMARY KEY,
+	data blob NOT NULL
+);
+
+CREATE TABLE snapshots (
+	id integer PRIMARY KEY,
+	link_id integer NOT NULL REFERENCES links(id) ON DELETE CASCADE,
+	-- the url that was fetched after following redirects
+	url text NOT NULL,
+	fetched text NOT NULL DEFAULT (datetime('now', 'utc')),
+	status integer,
+	-- json array of [name, value] pairs
+	headers text NOT NULL DEFAULT '[]',
+	content_type text,
+	digest text REFERENCES blobs(digest),
+	-- set instead of status when the fetch failed
+	error text
+);
+
+CREATE INDEX snapshots_link ON snapshots (link_id, fetched);
+
+CREATE TRIGGER snapshots_delete AFTER DELETE ON snapshots BEGIN
+	DELETE FROM blobs
+	WHERE digest = old.digest
+	AND NOT EXISTS (SELECT 1 FROM snapshots WHERE digest = old.digest);
+END;
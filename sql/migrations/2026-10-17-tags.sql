@@ -0,0 +1,13 @@
+CREATE TABLE tags (
+	id integer PRIMARY KEY,
+	user_id integer NOT NULL REFERENCES users(id),
+	name text NOT NULL,
+	created text NOT NULL DEFAULT (datetime('now', 'utc')),
+	UNIQUE (user_id, name)
+);
+
+CREATE TABLE link_tags (
+	link_id integer NOT NULL REFERENCES links(id) ON DELETE CASCADE,
+	tag_id integer NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+	PRIMARY KEY (link_id, tag_id)
+);
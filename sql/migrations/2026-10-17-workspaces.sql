@@ -0,0 +1,46 @@
+-- workspaces are accounts in users, like github organizations, so links,
+-- tags and collections work the same way for them. they can't log in, people
+-- reach them through their membership.
+--
+-- people and workspaces have their own names, so a workspace can't take the
+-- name of someone who hasn't logged in yet. that takes rebuilding users since
+-- the unique constraint on name can't be dropped otherwise.
+CREATE TABLE users_new (
+	id integer PRIMARY KEY,
+	name text NOT NULL,
+	created text NOT NULL DEFAULT (datetime('now', 'utc')),
+	deleted text,
+	feed_token text,
+	links_changed text,
+	-- set for workspaces, which also have a row in workspaces
+	workspace integer NOT NULL DEFAULT 0
+);
+
+INSERT INTO users_new (id, name, created, deleted, feed_token, links_changed)
+SELECT id, name, created, deleted, feed_token, links_changed
+FROM users;
+
+DROP TABLE users;
+
+-- the triggers which update users can't be checked until it's renamed
+PRAGMA legacy_alter_table = ON;
+ALTER TABLE users_new RENAME TO users;
+PRAGMA legacy_alter_table = OFF;
+
+CREATE UNIQUE INDEX users_name ON users (workspace, name);
+CREATE UNIQUE INDEX users_feed_token ON users (feed_token);
+
+CREATE TABLE workspaces (
+	id integer PRIMARY KEY REFERENCES users(id),
+	created_by integer NOT NULL REFERENCES users(id)
+);
+
+CREATE TABLE workspace_members (
+	workspace_id integer NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+	user_id integer NOT NULL REFERENCES users(id),
+	role text NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+	added text NOT NULL DEFAULT (datetime('now', 'utc')),
+	PRIMARY KEY (workspace_id, user_id)
+);
+
+CREATE INDEX workspace_members_user ON workspace_members (user_id);
@@ -13,18 +13,10 @@ if [ -e $dir/$file_name ]; then
 	exit 1
 fi
 
+# migrations are run inside a transaction by database::Db::new, which also
+# records them in the migrations table, so the file only needs the schema
+# changes themselves
+touch $dir/$file_name
 
-set +e
-read -r -d '' data<<EOF
-PRAGMA foreign_keys = ON;
-
-BEGIN EXCLUSIVE;
-
-INSERT INTO migrations (name) VALUES ('$file_name');
-
-END;
-EOF
-set -e
-
-echo "$data" > $dir/$file_name
-
+echo "created $dir/$file_name"
+echo "add \"$file_name\" to the migrations! list in src/database.rs"
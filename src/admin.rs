@@ -0,0 +1,43 @@
+//! who administers the server and who may register. people register by
+//! logging in through authn for the first time.
+
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// names of the users with the admin role, they can always register
+    pub admins : Vec<String>,
+    /// names which may register, anyone may if it's empty. a trailing * matches
+    /// any name starting with the rest.
+    pub allow :  Vec<String>,
+    /// names which may not register, this wins over allow
+    pub deny :   Vec<String>,
+}
+
+fn matches(patterns : &[String], name : &str) -> bool {
+    patterns.iter().any(|pattern| match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    })
+}
+
+impl Config {
+    pub fn is_admin(&self, name : &str) -> bool {
+        self.admins.iter().any(|admin| admin == name)
+    }
+
+    /// whether the name may be used for a new account, existing accounts
+    /// aren't affected
+    pub fn may_register(&self, name : &str) -> bool {
+        if self.is_admin(name) {
+            return true
+        }
+
+        if matches(&self.deny, name) {
+            return false
+        }
+
+        self.allow.is_empty() || matches(&self.allow, name)
+    }
+}
@@ -1,4 +1,4 @@
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use std::str::FromStr;
 use std::convert::TryInto;
@@ -8,16 +8,48 @@ use serde::Deserialize;
 use plumb::{Pipe, PipeExt};
 use plumb::tuple_utils::{Append, Prepend, Pluck};
 use hyper::Body;
-use hyper::body::Buf;
+use hyper::body::{Buf, HttpBody};
 use http::{header, StatusCode};
 use http_mux::{route, mux};
 use cookie::Cookie;
 
 use crate::error::Error;
-use crate::{database, ui};
+use crate::models::Role;
+use crate::time_utils::HTTP_DATE_FORMAT;
+use crate::{
+    admin,
+    api_tokens,
+    archiver,
+    bookmarks,
+    canonical,
+    checker,
+    csrf,
+    database,
+    diff,
+    export,
+    feed,
+    hashing,
+    html,
+    logging,
+    models,
+    netguard,
+    ratelimit,
+    ui,
+    warc,
+};
+
+mod v1;
 
 pub const COOKIE_NAME : &str = "ear7h-token";
 
+/// the header with the request's id, taken from the proxy if it sets one and
+/// always sent back
+const REQUEST_ID_HEADER : &str = "x-request-id";
+
+/// the most of a request body which is read into memory, enough for a big
+/// bookmarks export
+const MAX_BODY_SIZE : usize = 16 * 1024 * 1024;
+
 type Request = http::Request<Body>;
 type Response = http::Response<Body>;
 type Mux = mux::Mux<Error, (), Body, Response>;
@@ -26,46 +58,106 @@ pub struct ServerInner {
     pub db :           database::Db,
     pub render :       ui::Renderer,
     pub authn :        authn::client::Client,
+    pub archiver :     archiver::Archiver,
+    pub checker :      checker::Checker,
+    pub canonical :    canonical::Config,
+    pub admin :        admin::Config,
+    pub csrf :         csrf::Csrf,
+    /// throttles failed logins
+    pub limiter :      ratelimit::Limiter,
+    /// the host the server is reached at, for absolute urls
+    pub server_name :  String,
+    /// reverse proxies whose x-forwarded-for header gives the client's address
+    pub proxies :      Vec<IpAddr>,
 }
 
 pub type Server = Arc<ServerInner>;
 
 #[derive(Deserialize)]
 struct Config {
+    server_name : String,
     port : u16,
     database : String,
     authn : authn::client::Config,
+    #[serde(default)]
+    archiver : archiver::Config,
+    #[serde(default)]
+    netguard : netguard::Config,
+    #[serde(default)]
+    checker : checker::Config,
+    #[serde(default)]
+    canonical : canonical::Config,
+    #[serde(default)]
+    admin : admin::Config,
+    /// signs csrf tokens, a random one is used if it's not set
+    #[serde(default)]
+    csrf_secret : Option<String>,
+    #[serde(default)]
+    login_limits : ratelimit::Config,
+    /// the reverse proxies in front of the server, the x-forwarded-for
+    /// header is ignored on requests from anyone else
+    #[serde(default)]
+    trusted_proxies : Vec<IpAddr>,
+    #[serde(default)]
+    log : logging::Config,
 }
 
 pub fn new_server(config_file : &str) -> Result<(Server, SocketAddr), Error> {
     let file = std::fs::File::open(config_file)?;
     let conf : Config = serde_json::from_reader(file)?;
 
+    logging::init(&conf.log);
+
     let authn : authn::client::Client = conf.authn.try_into()?;
 
     let addr = SocketAddr::from(([127, 0, 0, 1], conf.port));
+    let guard = netguard::Guard::new(conf.netguard);
 
     let server = Arc::new(ServerInner {
         authn,
-        db :           database::Db::new(&conf.database)?,
+        db :           database::Db::new(&conf.database, &conf.canonical)?,
         render :       ui::Renderer::new(),
+        archiver :     archiver::Archiver::new(conf.archiver, guard.clone()),
+        checker :      checker::Checker::new(conf.checker, guard),
+        canonical :    conf.canonical,
+        admin :        conf.admin,
+        csrf :         csrf::Csrf::new(conf.csrf_secret.as_deref()),
+        limiter :      ratelimit::Limiter::new(conf.login_limits),
+        server_name :  conf.server_name,
+        proxies :      conf.trusted_proxies,
     });
 
+    tokio::spawn(archiver::run(server.clone()));
+    tokio::spawn(checker::run(server.clone()));
+
     Ok((server, addr))
 }
 
-/// url variable for user IDs which may be "self" or the user id
+/// url variable for user IDs which may be "self" or the user id. workspaces
+/// are accounts like users, so their ids go here too.
 struct UserId(Option<u32>);
 
 impl UserId {
-    /// compares the optional id in self id with a concrete one, if there's a match
-    /// the concrete id is returned.
-    fn compare(&self, id : u32) -> Option<u32> {
-        if self.0.is_none() || self.0.unwrap() == id {
-            Some(id)
+    /// the account in the url and the user's role in it, if the role is at
+    /// least the needed one. users own their account, other accounts are
+    /// workspaces they may be members of.
+    async fn authorize(
+        &self,
+        server : &Server,
+        user_id : u32,
+        needed : Role,
+    ) -> Result<(u32, Role), Error> {
+        let account = self.0.unwrap_or(user_id);
+
+        let role = if account == user_id {
+            Some(Role::Owner)
         } else {
-            None
-        }
+            server.db.get_role(account, user_id).await?
+        };
+
+        role.filter(|role| *role >= needed)
+            .map(|role| (account, role))
+            .ok_or(Error::Unauthorized)
     }
 }
 
@@ -83,7 +175,65 @@ impl FromStr for UserId {
     }
 }
 
-fn log_middleware<P>(next : P) -> impl Pipe<Input = (SocketAddr, Request), Output = P::Output>
+/// the address the request came from and whether it's one of the trusted
+/// proxies, set by log_middleware
+#[derive(Clone, Copy)]
+struct ClientAddr {
+    peer :  SocketAddr,
+    proxy : bool,
+}
+
+/// the client's address, as seen by the reverse proxy if it came through a
+/// trusted one
+fn client_ip(req : &Request) -> String {
+    let addr = match req.extensions().get::<ClientAddr>() {
+        Some(addr) => *addr,
+        None => return String::new(),
+    };
+
+    // anyone else can send the header with any address in it
+    if !addr.proxy {
+        return addr.peer.ip().to_string()
+    }
+
+    // the last address is the one the proxy added, the ones before it were
+    // sent by the client and may be made up
+    req.headers()
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.rsplit(',').next())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| addr.peer.ip().to_string())
+}
+
+/// the path with ids, tags and slugs replaced by placeholders, so requests
+/// can be grouped by route
+fn route_pattern(path : &str) -> String {
+    let mut prev = "";
+
+    path.split('/')
+        .map(|segment| {
+            let numeric = !segment.is_empty()
+                && segment.bytes().all(|b| b.is_ascii_digit());
+
+            let pattern = match prev {
+                _ if numeric => "{id}",
+                "tags" => "{tag}",
+                "collections" if !segment.is_empty() => "{slug}",
+                _ => segment,
+            };
+
+            prev = segment;
+            pattern
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// logs each request with its status and latency, and gives it an id which
+/// the logs of everything it does carry
+fn log_middleware<P>(server : Server, next : P) -> impl Pipe<Input = (SocketAddr, Request), Output = P::Output>
 where
     P : Pipe<Input = (Request,), Output = Response> + Send + Sync + 'static,
 {
@@ -92,43 +242,116 @@ where
     let next = Arc::new(next);
 
     plumb::id()
-    .aseq(|addr, req : Request| async move {
-        let pre_details = if let Some(addr) =  req.headers().get("x-forwarded-for") {
-            format!(
-                "{:?} {} {}",
-                addr,
-                req.method(),
-                req.uri().path(),
-            )
-        } else {
-            format!(
-                "{:?} {} {}",
-                addr,
-                req.method(),
-                req.uri().path(),
-            )
-        };
+    .aseq(|addr, mut req : Request| async move {
+        req.extensions_mut().insert(ClientAddr {
+            peer :  addr,
+            proxy : server.proxies.contains(&addr.ip()),
+        });
+
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|id| id.to_str().ok())
+            .filter(|id| logging::valid_request_id(id))
+            .map(str::to_string)
+            .unwrap_or_else(logging::new_request_id);
+
+        let method = req.method().to_string();
+        let path = req.uri().path().to_string();
+        let ip = client_ip(&req);
+
+        let mut res = logging::scope(request_id.clone(), async {
+            let start = tokio::time::Instant::now();
+
+            let res = next.run((req,)).await;
+
+            let ms = start.elapsed().as_secs_f64() * 1000.0;
+            log!(
+                Info,
+                "request",
+                method = method,
+                route = route_pattern(&path),
+                path = path,
+                status = res.status().as_u16(),
+                ip = ip,
+                ms = format!("{:.1}", ms),
+            );
+
+            res
+        }).await;
+
+        res.headers_mut()
+            .insert(REQUEST_ID_HEADER, request_id.parse().unwrap());
+
+        res
+    })
+}
+
+
+fn with_authn<S>(server_orig : Server) ->
+    impl Fn(S) -> plumb::PinBoxFut<
+        Result<
+            <S as Append<u32>>::Output,
+            Error
+        >
+    > + Clone
+where
+    S : Pluck<Head = Request> + Append<u32> + Send + 'static,
+    <S as Pluck>::Tail : Send,
+{
+    move |s : S| {
+        let server = server_orig.clone();
+        Box::pin(async move {
+            let (req, tail) = s.pluck();
+
+            // personal api tokens, for clients that aren't browsers
+            if let Some(auth) = req.headers().get(header::AUTHORIZATION) {
+                let token = auth
+                    .to_str()
+                    .ok()
+                    .and_then(|s| s.strip_prefix("Bearer "))
+                    .ok_or(Error::FailedLogin)?;
 
-        let start = tokio::time::Instant::now();
+                let hash = api_tokens::hash(token.trim());
+                let user_id = server.db.use_api_token(&hash).await?;
 
-        let res = next.run((req,)).await;
+                let user = server.db.get_user(user_id).await?;
+                if user.deleted.is_some() {
+                    return Err(Error::UserDisabled(user.name))
+                }
 
-        let end = tokio::time::Instant::now();
-        let delta = end - start;
+                logging::set_user(user_id);
+                return Ok(tail.prepend(req).append(user_id))
+            }
 
-        println!(
-            "{} {} {:?}",
-            res.status(),
-            pre_details,
-            delta
-        );
+            let token = get_cookie(&req, COOKIE_NAME)
+                .ok_or(Error::FailedLogin)?;
+
+            let name = server.authn.validate_token(&token).await?;
+            // workspaces aren't found, and can't log in
+            let user = server.db
+                .get_user_by_name(&name)
+                .await
+                .map_err(|err| match err {
+                    Error::UserNameNotFound(_) => Error::FailedLogin,
+                    err => err,
+                })?;
+
+            if user.deleted.is_some() {
+                return Err(Error::UserDisabled(user.name))
+            }
 
-        res
-    })
+            logging::set_user(user.id);
+            Ok(tail.prepend(req).append(user.id))
+        })
+    }
 }
 
 
-fn with_authn<S>(server_orig : Server) ->
+
+/// like with_authn, but also takes the account's feed token from the token
+/// query parameter, since feed readers can't log in
+fn with_feed_authn<S>(server_orig : Server) ->
     impl Fn(S) -> plumb::PinBoxFut<
         Result<
             <S as Append<u32>>::Output,
@@ -142,37 +365,160 @@ where
     move |s : S| {
         let server = server_orig.clone();
         Box::pin(async move {
+            #[derive(Deserialize)]
+            struct FeedQuery {
+                token : Option<String>,
+            }
+
             let (req, tail) = s.pluck();
 
-            for cookie in req.headers().get_all(http::header::COOKIE) {
-                let cookie_str = if let Ok(s) = cookie.to_str() {
-                    s
-                } else {
-                    continue
-                };
+            let token = req.uri()
+                .query()
+                .and_then(|q| serde_urlencoded::from_str::<FeedQuery>(q).ok())
+                .and_then(|q| q.token);
 
-                match Cookie::parse(cookie_str) {
-                    Ok(c) if c.name() == COOKIE_NAME => {
+            let token = match token {
+                Some(token) => token,
+                None => return with_authn(server)(tail.prepend(req)).await,
+            };
 
-                        let name = server.authn.validate_token(c.value()).await?;
-                        let user_id = server.db.get_user_by_name(&name).await?.id;
+            let user_id = server.db.use_feed_token(&token).await?;
 
-                        return Ok(tail.prepend(req).append(user_id))
-                    },
-                    _ => {}
-                }
+            let user = server.db.get_user(user_id).await?;
+            if user.deleted.is_some() {
+                return Err(Error::UserDisabled(user.name))
+            }
+
+            logging::set_user(user_id);
+            Ok(tail.prepend(req).append(user_id))
+        })
+    }
+}
+
+/// checks the csrf token of form posts and json writes, it goes after
+/// with_authn. scripts send it in the csrf header. clients using api tokens
+/// aren't browsers, and don't need one.
+fn with_csrf<S>(server_orig : Server) ->
+    impl Fn(S) -> plumb::PinBoxFut<Result<S, Error>> + Clone
+where
+    S : Pluck<Head = Request> + Send + 'static,
+    <S as Pluck>::Tail : Send,
+{
+    move |s : S| {
+        let server = server_orig.clone();
+        Box::pin(async move {
+            let (req, tail) = s.pluck();
+
+            if req.headers().contains_key(header::AUTHORIZATION) {
+                return Ok(tail.prepend(req))
+            }
+
+            let session = csrf_session(&req).ok_or(Error::CsrfMismatch)?;
+
+            // the body is read for the token, and put back for the handler
+            let (parts, body) = req.into_parts();
+            let body = read_body(body).await?;
+
+            let token = match parts.headers.get(csrf::HEADER) {
+                Some(token) => token.to_str().ok().map(str::to_string),
+                None => read_csrf_field(&parts.headers, &body).await,
+            };
+
+            let valid = token
+                .map(|token| server.csrf.verify(&session, &token))
+                .unwrap_or(false);
+            if !valid {
+                return Err(Error::CsrfMismatch)
             }
 
-            return Err(Error::FailedLogin)
+            Ok(tail.prepend(Request::from_parts(parts, body.into())))
         })
     }
 }
 
+/// reads the whole body, refusing ones bigger than MAX_BODY_SIZE before
+/// they're buffered
+async fn read_body(mut body : Body) -> Result<hyper::body::Bytes, Error> {
+    if body.size_hint().lower() > MAX_BODY_SIZE as u64 {
+        return Err(Error::PayloadTooLarge)
+    }
+
+    let mut buf = Vec::new();
+
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+
+        if buf.len() + chunk.len() > MAX_BODY_SIZE {
+            return Err(Error::PayloadTooLarge)
+        }
+
+        buf.extend_from_slice(&chunk);
+    }
+
+    Ok(buf.into())
+}
+
+/// the token field of a urlencoded or multipart form
+async fn read_csrf_field(
+    headers : &http::HeaderMap,
+    body : &hyper::body::Bytes,
+) -> Option<String> {
+    let boundary = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|ct| ct.to_str().ok())
+        .and_then(|ct| multer::parse_boundary(ct).ok());
+
+    let boundary = match boundary {
+        Some(boundary) => boundary,
+        None => {
+            return serde_urlencoded::from_bytes::<Vec<(String, String)>>(body)
+                .ok()?
+                .into_iter()
+                .find(|(name, _)| name == csrf::FIELD)
+                .map(|(_, value)| value)
+        },
+    };
+
+    let mut multipart =
+        multer::Multipart::new(Body::from(body.clone()), boundary);
+
+    while let Some(part) = multipart.next_field().await.ok()? {
+        if part.name() == Some(csrf::FIELD) {
+            return part.text().await.ok()
+        }
+    }
+
+    None
+}
+
+/// the value of the named cookie, browsers send all of them in one header
+fn get_cookie(req : &Request, name : &str) -> Option<String> {
+    req.headers()
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|cookies| cookies.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| Cookie::parse(cookie.trim()).ok())
+        .find(|cookie| cookie.name() == name)
+        .map(|cookie| cookie.value().to_string())
+}
+
+/// what csrf tokens are bound to, see the csrf module
+fn csrf_session(req : &Request) -> Option<String> {
+    get_cookie(req, COOKIE_NAME)
+        .or_else(|| get_cookie(req, csrf::COOKIE_NAME))
+}
 
+/// the token for the forms in the page answering req
+fn csrf_token(server : &Server, req : &Request) -> String {
+    csrf_session(req)
+        .map(|session| server.csrf.token(&session))
+        .unwrap_or_default()
+}
 
 pub fn routes(server : Server) -> impl Pipe<Input = (SocketAddr, Request), Output = Response> {
     macro_rules! register_routes {
-        ($($route:ident,)*) => {
+        ($($route:path,)*) => {
             {
                 let mux = mux::new_mux::<Error, _, _>();
 
@@ -186,9 +532,68 @@ pub fn routes(server : Server) -> impl Pipe<Input = (SocketAddr, Request), Outpu
     let mux = register_routes!{
         get_users_links,
         post_users_links,
+        get_users_import,
+        post_users_import,
+        get_users_export_html,
+        get_users_export_json,
+        get_users_export_csv,
+        get_users_export_warc,
+        get_users_tag_links,
+        get_users_collections,
+        post_users_collections,
+        post_users_collection_edit,
+        post_users_collection_delete,
+        get_users_collection_links,
+        post_users_collection_link_add,
+        post_users_collection_link_remove,
+        get_collection_links,
+        get_users_public,
+        get_users_workspaces,
+        post_users_workspaces,
+        get_workspaces_links,
+        get_users_members,
+        post_users_members,
+        post_users_member_remove,
+        get_admin_users,
+        post_admin_user_disable,
+        post_admin_user_enable,
+        get_users_links_atom,
+        get_users_links_rss,
+        get_users_tag_links_atom,
+        get_users_tag_links_rss,
+        get_collection_links_atom,
+        get_collection_links_rss,
+        get_users_search,
+        get_users_link_edit,
+        post_users_link_edit,
+        get_users_link_snapshot,
+        get_users_link_archive,
+        get_users_link_reader,
+        get_users_link_history,
+        post_users_link_snapshots,
+        get_users_link_snapshot_version,
+        get_users_link_snapshot_archive,
+        get_users_link_snapshot_reader,
+        get_users_link_diff,
+        get_users_trash,
+        get_users_tokens,
+        post_users_tokens,
+        post_users_token_revoke,
+        post_users_feed_token,
+        delete_users_link,
+        post_users_link_delete,
+        post_users_link_restore,
+        post_users_link_purge,
         get_login,
         post_login,
         get_logout,
+        v1::get_user,
+        v1::get_links,
+        v1::post_links,
+        v1::get_link,
+        v1::put_link,
+        v1::delete_link,
+        v1::post_login,
     }
     .tuple()
     .seq(|res : Result<Response, Error>| {
@@ -198,9 +603,60 @@ pub fn routes(server : Server) -> impl Pipe<Input = (SocketAddr, Request), Outpu
         }
     });
 
-    log_middleware(mux)
+    log_middleware(server, mux)
+}
+
+
+/// the number of links on each page of the html listings
+const LINKS_PAGE_SIZE : u32 = 50;
+
+/// query parameters of the paginated link listings
+#[derive(Deserialize)]
+struct PageQuery {
+    #[serde(default)]
+    sort :   models::Sort,
+    health : Option<models::Health>,
+    cursor : Option<String>,
+}
+
+impl PageQuery {
+    fn parse(req : &Request) -> Result<Self, Error> {
+        serde_urlencoded::from_str(req.uri().query().unwrap_or(""))
+            .map_err(|_| Error::BadRequest)
+    }
 }
 
+/// the html listing of an account's links
+async fn links_page(
+    server : Server,
+    req : Request,
+    user_id : u32,
+    role : Role,
+) -> Result<Response, Error> {
+    let query = PageQuery::parse(&req)?;
+    let links = server.db.get_links_page(
+        user_id,
+        None,
+        None,
+        query.health,
+        query.sort,
+        query.cursor.as_deref(),
+        LINKS_PAGE_SIZE,
+    ).await?;
+
+    let user = server.db.get_user(user_id).await?;
+    let tags = server.db.get_tags(user_id).await?;
+    let page = server.render.users_links(
+        &user,
+        &links,
+        None,
+        &tags,
+        role >= Role::Editor,
+        &csrf_token(&server, &req),
+    );
+
+    Ok(Response::new(page.into()))
+}
 
 fn get_users_links(server : Server, m : Mux) -> Mux {
 
@@ -208,19 +664,16 @@ fn get_users_links(server : Server, m : Mux) -> Mux {
         route!(GET / "users" / UserId / "links.html"),
         mux::new_handler()
         .map_tuple().aand_then(with_authn(server.clone()))
-        .and_then(|req, url_id : UserId, token_id : u32| {
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
             // authz
-            url_id.compare(token_id)
-            .map(|id| Ok((req, id)))
-            .unwrap_or(Err(Error::Unauthorized))
+            let (id, role) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, role, server))
         })
-        .map_bind(server.clone())
-        .aand_then(|_req, user_id, server : Server| async move {
-            let links = server.db.get_links(user_id).await?;
-            let user = server.db.get_user(user_id).await?;
-            let page = server.render.users_links(&user, links.as_slice(), true);
-
-            Ok(Response::new(page.into()))
+        .aand_then(|req : Request, user_id, role : Role, server : Server| async move {
+            links_page(server, req, user_id, role).await
         })
     )
 }
@@ -230,16 +683,19 @@ fn post_users_links(server : Server, m : Mux) -> Mux {
         route!(POST / "users" / UserId / "links.html"),
         mux::new_handler()
         .map_tuple().aand_then(with_authn(server.clone()))
-        .and_then(|req, url_id : UserId, token_id : u32| {
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
             // authz
-            if let Some(user_id) = url_id.compare(token_id) {
-                Ok((req, user_id))
-            } else {
-                Err(Error::Unauthorized)
-            }
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, server))
         })
-        .map_bind(server.clone())
         .aand_then(|req : Request, user_id, server : Server| async move {
+            // the body is read below, which takes the request
+            let csrf = csrf_token(&server, &req);
+
             let reader = hyper::body::aggregate(req.into_body()).await?.reader();
 
             #[derive(Deserialize)]
@@ -250,157 +706,2253 @@ fn post_users_links(server : Server, m : Mux) -> Mux {
             let form : PostLinksForm = serde_urlencoded::from_reader(reader)
                 .map_err(|_| Error::BadRequest)?;
 
-            for line in form.links.lines().filter(|l| !l.is_empty()) {
-                let u = url::Url::parse(&line)
-                    .map_err(|_| Error::InvalidUrl(line.to_string()))?;
+            // each line is a url followed by optional #tags
+            for line in form.links.lines() {
+                let mut words = line.split_whitespace();
+                let link = match words.next() {
+                    Some(link) => link,
+                    None => continue,
+                };
+
+                let u = parse_link_url(link)?;
 
-                match server.db.insert_link(user_id, u.as_str()).await {
-                    Ok(_) | Err(Error::DuplicateUrl(_)) => {},
+                let tags = words
+                    .map(parse_tag)
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let canonical = canonical::canonicalize(&u, &server.canonical);
+                let inserted = server.db
+                    .insert_link(user_id, u.as_str(), &canonical, None)
+                    .await;
+                let link_id = match inserted {
+                    Ok(id) => id,
+                    Err(Error::DuplicateUrl(_, id)) => id,
                     Err(err) => return Err(err)
+                };
+
+                for tag in tags.iter() {
+                    server.db.add_tag(user_id, link_id, tag).await?;
                 }
             }
 
-            let links = server.db.get_links(user_id).await?;
+            server.archiver.wake();
+
+            let links = server.db.get_links_page(
+                user_id,
+                None,
+                None,
+                None,
+                models::Sort::default(),
+                None,
+                LINKS_PAGE_SIZE,
+            ).await?;
             let user = server.db.get_user(user_id).await?;
+            let tags = server.db.get_tags(user_id).await?;
 
-            let page = server.render.users_links(&user, links.as_slice(), true);
+            let page = server.render.users_links(
+                &user,
+                &links,
+                None,
+                &tags,
+                true,
+                &csrf,
+            );
 
             Ok(Response::new(page.into()))
         })
     )
 }
 
-fn get_login(server : Server, m : Mux) -> Mux {
-
+fn get_users_import(server : Server, m : Mux) -> Mux {
     m.handle(
-        route!(GET / "login.html"),
+        route!(GET / "users" / UserId / "import.html"),
         mux::new_handler()
-        .map_tuple().amap(with_authn(server.clone()))
-        .map_tuple().map_bind(server.clone())
-        .map(|cookie : Result<(_, u32), _>, server : Server| {
-            if cookie.is_ok() {
-                http::response::Builder::new()
-                    .header(header::LOCATION, "/users/self/links.html")
-                    .status(StatusCode::SEE_OTHER)
-                    .body("redirecting".into()).unwrap()
-            } else {
-                Response::new(server.render.login().into())
-            }
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|req, user_id, server : Server| async move {
+            let user = server.db.get_user(user_id).await?;
+            let page = server.render.users_import(
+                &user,
+                None,
+                &csrf_token(&server, &req),
+            );
+
+            Ok(Response::new(page.into()))
         })
     )
 }
 
-fn get_logout(_server : Server, m : Mux) -> Mux {
-
+fn post_users_import(server : Server, m : Mux) -> Mux {
     m.handle(
-        route!(GET / "logout.html"),
+        route!(POST / "users" / UserId / "import.html"),
         mux::new_handler()
-        .map(|_| {
-            let cookie = Cookie::build(COOKIE_NAME, "<logged out>")
-                .http_only(true)
-                .same_site(cookie::SameSite::Strict)
-                .path("/")
-                .finish()
-                .to_string();
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|req : Request, user_id, server : Server| async move {
+            // the body is read below, which takes the request
+            let csrf = csrf_token(&server, &req);
 
-            http::response::Builder::new()
-                .header(header::LOCATION, "/login.html")
-                .status(StatusCode::SEE_OTHER)
-                .header(header::SET_COOKIE, cookie)
-                .body("redirecting".into()).unwrap()
+            let file = read_upload(req, "bookmarks").await?;
+
+            let mut report = bookmarks::ImportReport::default();
+
+            for bookmark in bookmarks::parse(&file) {
+                // browsers also export things like place: and javascript:
+                // bookmarks, which aren't worth archiving
+                let u = match parse_link_url(&bookmark.url) {
+                    Ok(u) => u,
+                    Err(_) => {
+                        report.invalid.push(bookmark.url);
+                        continue
+                    },
+                };
+
+                let created = bookmark.add_date
+                    .and_then(|d| {
+                        time::OffsetDateTime::from_unix_timestamp(d).ok()
+                    })
+                    .map(models::Time::from);
+
+                let canonical = canonical::canonicalize(&u, &server.canonical);
+                let inserted = server.db
+                    .insert_link(
+                        user_id,
+                        u.as_str(),
+                        &canonical,
+                        created.as_ref(),
+                    )
+                    .await;
+
+                let link_id = match inserted {
+                    Ok(id) => {
+                        report.new += 1;
+
+                        server.db.update_link(
+                            user_id,
+                            id,
+                            bookmark.title.as_deref(),
+                            bookmark.description.as_deref(),
+                            None,
+                        ).await?;
+
+                        id
+                    },
+                    Err(Error::DuplicateUrl(_, id)) => {
+                        report.duplicate += 1;
+                        id
+                    },
+                    Err(err) => return Err(err),
+                };
+
+                for tag in bookmark.tags.iter() {
+                    server.db.add_tag(user_id, link_id, tag).await?;
+                }
+            }
+
+            server.archiver.wake();
+
+            let user = server.db.get_user(user_id).await?;
+            let page = server.render.users_import(&user, Some(&report), &csrf);
+
+            Ok(Response::new(page.into()))
         })
     )
 }
 
-fn post_login(server : Server, m : Mux) -> Mux {
-    #[derive(Deserialize)]
-    struct Req {
-        username : String,
-        password : String,
-    }
+/// reads the file named field from a multipart form, other content types are
+/// taken to be the file itself so the endpoint is easy to use with curl
+async fn read_upload(req : Request, field : &str) -> Result<String, Error> {
+    let boundary = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|ct| ct.to_str().ok())
+        .and_then(|ct| multer::parse_boundary(ct).ok());
+
+    let boundary = match boundary {
+        Some(boundary) => boundary,
+        None => {
+            let body = hyper::body::to_bytes(req.into_body()).await?;
+            return String::from_utf8(body.to_vec())
+                .map_err(|_| Error::BadRequest)
+        },
+    };
 
-    struct Res {
-        token : String,
+    let mut multipart = multer::Multipart::new(req.into_body(), boundary);
+
+    while let Some(part) = multipart
+        .next_field()
+        .await
+        .map_err(|_| Error::BadRequest)?
+    {
+        if part.name() == Some(field) {
+            return part.text().await.map_err(|_| Error::BadRequest)
+        }
     }
 
-    impl Into<Response> for Res {
-        fn into(self) -> Response {
-            let cookie = Cookie::build(COOKIE_NAME, self.token.clone())
-                .http_only(true)
-                .same_site(cookie::SameSite::Strict)
-                .path("/")
-                .finish()
-                .to_string();
+    Err(Error::BadRequest)
+}
 
-            http::response::Builder::new()
-                .header(header::LOCATION, "/users/self/links.html")
-                .header(header::SET_COOKIE, cookie)
-                .status(StatusCode::SEE_OTHER)
-                .body("redirecting".into()).unwrap()
+/// streams all of the user's links as a file download
+async fn export(
+    server : Server,
+    user_id : u32,
+    format : export::Format,
+) -> Result<Response, Error> {
+    let links = server.db.get_all_links(user_id).await?;
+
+    let (mut tx, body) = Body::channel();
+
+    tokio::spawn(async move {
+        let chunks = std::iter::once(format.header().to_string())
+            .chain(links.iter().map(|link| format.entry(link)))
+            .chain(std::iter::once(format.footer().to_string()))
+            .filter(|chunk| !chunk.is_empty());
+
+        for chunk in chunks {
+            if tx.send_data(chunk.into()).await.is_err() {
+                // the client went away
+                break
+            }
         }
-    }
+    });
+
+    Ok(http::response::Builder::new()
+        .header(header::CONTENT_TYPE, format.content_type())
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", format.file_name()),
+        )
+        .body(body).unwrap())
+}
 
+fn get_users_export_html(server : Server, m : Mux) -> Mux {
     m.handle(
-        route!(POST / "login.html"),
+        route!(GET / "users" / UserId / "export.html"),
         mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
         .map_bind(server.clone())
-        .aand_then(|req : Request, server : Server| async move {
-            let reader = hyper::body::aggregate(req.into_body()).await?.reader();
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|_req, user_id, server : Server| {
+            export(server, user_id, export::Format::Html)
+        })
+    )
+}
 
-            let form : Req = serde_urlencoded::from_reader(reader)
-                .map_err(|_| Error::BadRequest)?;
+fn get_users_export_json(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "export.json"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|_req, user_id, server : Server| {
+            export(server, user_id, export::Format::Json)
+        })
+    )
+}
+
+fn get_users_export_csv(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "export.csv"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|_req, user_id, server : Server| {
+            export(server, user_id, export::Format::Csv)
+        })
+    )
+}
+
+/// streams the user's snapshots as a gzipped warc file
+fn get_users_export_warc(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "export.warc.gz"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|_req, user_id, server : Server| async move {
+            let snapshots = server.db.get_user_snapshots(user_id).await?;
 
-            let token = server.authn.login(
-                &form.username,
-                &form.password,
-                Duration::from_secs(60 * 60 * 24 * 7),
-            ).await.map_err(|err| {
-                eprintln!("{:?}", err);
-                Error::FailedLogin
-            })?;
+            let (mut tx, body) = Body::channel();
 
-            server.db.upsert_user(&form.username).await?;
+            tokio::spawn(async move {
+                let now = time::OffsetDateTime::now_utc();
 
-            Ok(Res{token}.into())
+                if tx.send_data(warc::warcinfo(&now).into()).await.is_err() {
+                    return
+                }
+
+                // blobs are loaded one at a time since they can be large
+                for snapshot in snapshots.iter() {
+                    let digest = match &snapshot.digest {
+                        Some(digest) => digest,
+                        None => continue,
+                    };
+
+                    let blob = match server.db.get_blob(digest).await {
+                        Ok(blob) => blob,
+                        Err(err) => {
+                            log!(
+                                Error,
+                                "export failed",
+                                error = format!("{:?}", err),
+                            );
+                            tx.abort();
+                            return
+                        },
+                    };
+
+                    let records = warc::snapshot(&now, snapshot, &blob);
+                    if tx.send_data(records.into()).await.is_err() {
+                        // the client went away
+                        return
+                    }
+                }
+            });
+
+            Ok(http::response::Builder::new()
+                .header(header::CONTENT_TYPE, warc::CONTENT_TYPE)
+                .header(
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", warc::FILE_NAME),
+                )
+                .body(body).unwrap())
         })
     )
 }
 
+/// parses the url of a link being saved. only http and https urls are
+/// accepted, others like javascript: would run when a visitor of a shared
+/// collection follows the link.
+fn parse_link_url(url : &str) -> Result<url::Url, Error> {
+    url::Url::parse(url)
+        .ok()
+        .filter(|u| matches!(u.scheme(), "http" | "https"))
+        .ok_or_else(|| Error::InvalidUrl(url.to_string()))
+}
 
-fn render_error(err : Error) -> Response {
-    use http::StatusCode as S;
-    use Error::*;
+/// parses a "#tag" word from the add links form
+fn parse_tag(word : &str) -> Result<String, Error> {
+    word.strip_prefix('#')
+        .map(str::to_lowercase)
+        .filter(|tag| models::valid_tag(tag))
+        .ok_or_else(|| Error::InvalidTag(word.to_string()))
+}
 
-    eprintln!("{:?}", &err);
+fn get_users_tag_links(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "tags" / String / "links.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, tag : String, token_id : u32, server : Server| async move {
+            // authz
+            let (id, role) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, role, tag, server))
+        })
+        .aand_then(|req : Request, user_id, role : Role, tag : String, server : Server| async move {
+            let query = PageQuery::parse(&req)?;
+            let links = server.db.get_links_page(
+                user_id,
+                Some(&tag),
+                None,
+                query.health,
+                query.sort,
+                query.cursor.as_deref(),
+                LINKS_PAGE_SIZE,
+            ).await?;
 
-    let status;
-    let body;
+            let user = server.db.get_user(user_id).await?;
+            let tags = server.db.get_tags(user_id).await?;
+            let page = server.render.users_links(
+                &user,
+                &links,
+                None,
+                &tags,
+                role >= Role::Editor,
+                &csrf_token(&server, &req),
+            );
 
-    match err {
-        InvalidUrl(s) => {
-            status = S::BAD_REQUEST;
-            body = format!("invalid url: {}", s);
-        },
-        DuplicateUrl(s) => {
-            status = S::CONFLICT;
-            body = format!("duplicate url: {}", s);
-        },
-        RouteNotFound => {
-           status = S::NOT_FOUND;
-           body = "route not found".to_string();
-        },
-        FailedLogin => {
-           status = S::UNAUTHORIZED;
-           body = include_str!("../ui/failed-login.html").to_string();
-        },
-        _ => {
-            status = S::INTERNAL_SERVER_ERROR;
-            body   = "internal server error".to_string();
-        }
-    }
+            Ok(Response::new(page.into()))
+        })
+    )
+}
+
+/// the collection form, for creating and editing
+#[derive(Deserialize)]
+struct CollectionForm {
+    name :       String,
+    visibility : models::Visibility,
+}
+
+impl CollectionForm {
+    async fn read(req : Request) -> Result<Self, Error> {
+        let reader = hyper::body::aggregate(req.into_body()).await?.reader();
+
+        let form : Self = serde_urlencoded::from_reader(reader)
+            .map_err(|_| Error::BadRequest)?;
+
+        if form.name.trim().is_empty() {
+            return Err(Error::BadRequest)
+        }
+
+        Ok(form)
+    }
+}
+
+fn get_users_collections(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "collections.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|req, user_id, server : Server| async move {
+            let collections = server.db.get_collections(user_id, None).await?;
+            let user = server.db.get_user(user_id).await?;
+            let page = server.render.users_collections(
+                &user,
+                &collections,
+                &csrf_token(&server, &req),
+            );
+
+            Ok(Response::new(page.into()))
+        })
+    )
+}
+
+fn post_users_collections(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "collections.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|req : Request, user_id, server : Server| async move {
+            let form = CollectionForm::read(req).await?;
+
+            server.db.insert_collection(
+                user_id,
+                form.name.trim(),
+                form.visibility,
+            ).await?;
+
+            Ok(see_other(&format!("/users/{}/collections.html", user_id)))
+        })
+    )
+}
+
+fn post_users_collection_edit(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "collections" / u32 / "edit.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, collection_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, collection_id, server))
+        })
+        .aand_then(|req : Request, user_id, collection_id, server : Server| async move {
+            let form = CollectionForm::read(req).await?;
+
+            server.db.update_collection(
+                user_id,
+                collection_id,
+                form.name.trim(),
+                form.visibility,
+            ).await?;
+
+            Ok(see_other(&format!("/users/{}/collections.html", user_id)))
+        })
+    )
+}
+
+fn post_users_collection_delete(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "collections" / u32 / "delete.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, collection_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, collection_id, server))
+        })
+        .aand_then(|_req, user_id, collection_id, server : Server| async move {
+            server.db.delete_collection(user_id, collection_id).await?;
+
+            Ok(see_other(&format!("/users/{}/collections.html", user_id)))
+        })
+    )
+}
+
+fn get_users_collection_links(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "collections" / u32 / "links.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, collection_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, role) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, role, collection_id, server))
+        })
+        .aand_then(|req : Request, user_id, role : Role, collection_id, server : Server| async move {
+            let collection =
+                server.db.get_collection(user_id, collection_id).await?;
+
+            let query = PageQuery::parse(&req)?;
+            let links = server.db.get_links_page(
+                user_id,
+                None,
+                Some(collection.id),
+                query.health,
+                query.sort,
+                query.cursor.as_deref(),
+                LINKS_PAGE_SIZE,
+            ).await?;
+
+            let user = server.db.get_user(user_id).await?;
+            let page = server.render.users_links(
+                &user,
+                &links,
+                Some(&collection),
+                &[],
+                role >= Role::Editor,
+                &csrf_token(&server, &req),
+            );
+
+            Ok(Response::new(page.into()))
+        })
+    )
+}
+
+fn post_users_collection_link_add(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "collections" / u32 / "links" / u32 / "add.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, collection_id : u32, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, collection_id, link_id, server))
+        })
+        .aand_then(|_req, user_id, collection_id, link_id, server : Server| async move {
+            server.db
+                .add_collection_link(user_id, collection_id, link_id)
+                .await?;
+
+            Ok(see_other(&format!(
+                "/users/{}/links/{}/edit.html",
+                user_id,
+                link_id,
+            )))
+        })
+    )
+}
+
+fn post_users_collection_link_remove(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "collections" / u32 / "links" / u32 / "remove.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, collection_id : u32, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, collection_id, link_id, server))
+        })
+        .aand_then(|_req, user_id, collection_id, link_id, server : Server| async move {
+            server.db
+                .remove_collection_link(user_id, collection_id, link_id)
+                .await?;
+
+            Ok(see_other(&format!(
+                "/users/{}/links/{}/edit.html",
+                user_id,
+                link_id,
+            )))
+        })
+    )
+}
+
+/// a public or unlisted collection, readable without logging in
+fn get_collection_links(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "collections" / String / "links.html"),
+        mux::new_handler()
+        .map_bind(server.clone())
+        .aand_then(|req : Request, slug : String, server : Server| async move {
+            let collection = server.db.get_shared_collection(&slug).await?;
+
+            let query = PageQuery::parse(&req)?;
+            let links = server.db.get_links_page(
+                collection.user_id,
+                None,
+                Some(collection.id),
+                query.health,
+                query.sort,
+                query.cursor.as_deref(),
+                LINKS_PAGE_SIZE,
+            ).await?;
+
+            let user = server.db.get_user(collection.user_id).await?;
+            let page = server.render.users_links(
+                &user,
+                &links,
+                Some(&collection),
+                &[],
+                false,
+                &csrf_token(&server, &req),
+            );
+
+            Ok(Response::new(page.into()))
+        })
+    )
+}
+
+/// lists the user's public collections, readable without logging in
+fn get_users_public(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / u32 / "public.html"),
+        mux::new_handler()
+        .map_bind(server.clone())
+        .aand_then(|_req, user_id : u32, server : Server| async move {
+            let user = server.db.get_user(user_id).await?;
+            // disabled accounts aren't shown to anyone
+            if user.deleted.is_some() {
+                return Err(Error::UserIdNotFound(user_id))
+            }
+
+            let collections = server.db
+                .get_collections(user_id, Some(models::Visibility::Public))
+                .await?;
+            let page = server.render.users_public(&user, &collections);
+
+            Ok(Response::new(page.into()))
+        })
+    )
+}
+
+/// workspaces are made by people, not by other workspaces, so this is only
+/// for the user's own account
+fn authorize_self(url_id : &UserId, user_id : u32) -> Result<u32, Error> {
+    match url_id.0 {
+        Some(id) if id != user_id => Err(Error::Unauthorized),
+        _ => Ok(user_id),
+    }
+}
+
+fn get_users_workspaces(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "workspaces.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let id = authorize_self(&url_id, token_id)?;
+            Ok((req, id, server))
+        })
+        .aand_then(|req, user_id, server : Server| async move {
+            let workspaces = server.db.get_workspaces(user_id).await?;
+            let user = server.db.get_user(user_id).await?;
+            let page = server.render.users_workspaces(
+                &user,
+                &workspaces,
+                &csrf_token(&server, &req),
+            );
+
+            Ok(Response::new(page.into()))
+        })
+    )
+}
+
+fn post_users_workspaces(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "workspaces.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let id = authorize_self(&url_id, token_id)?;
+            Ok((req, id, server))
+        })
+        .aand_then(|req : Request, user_id, server : Server| async move {
+            let reader = hyper::body::aggregate(req.into_body()).await?.reader();
+
+            #[derive(Deserialize)]
+            struct WorkspaceForm {
+                name : String,
+            }
+
+            let form : WorkspaceForm = serde_urlencoded::from_reader(reader)
+                .map_err(|_| Error::BadRequest)?;
+
+            let name = form.name.trim();
+            if name.is_empty() {
+                return Err(Error::BadRequest)
+            }
+
+            let workspace_id = server.db.insert_workspace(user_id, name).await?;
+
+            Ok(see_other(&format!("/users/{}/members.html", workspace_id)))
+        })
+    )
+}
+
+// workspaces are accounts, their links live at the /users pages
+fn get_workspaces_links(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "workspaces" / u32 / "links.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|_req, workspace_id : u32, token_id : u32, server : Server| async move {
+            // only members see that the workspace exists
+            server.db.get_workspace(workspace_id, token_id).await?;
+
+            Ok(see_other(&format!("/users/{}/links.html", workspace_id)))
+        })
+    )
+}
+
+fn get_users_members(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "members.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, token_id, server))
+        })
+        .aand_then(|req, workspace_id, user_id, server : Server| async move {
+            let workspace =
+                server.db.get_workspace(workspace_id, user_id).await?;
+            let members = server.db.get_members(workspace_id).await?;
+            let user = server.db.get_user(user_id).await?;
+            let page = server.render.users_members(
+                &user,
+                &workspace,
+                &members,
+                &csrf_token(&server, &req),
+            );
+
+            Ok(Response::new(page.into()))
+        })
+    )
+}
+
+fn post_users_members(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "members.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Owner)
+                .await?;
+            Ok((req, id, token_id, server))
+        })
+        .aand_then(|req : Request, workspace_id, user_id, server : Server| async move {
+            // a user's own account isn't a workspace
+            server.db.get_workspace(workspace_id, user_id).await?;
+
+            let reader = hyper::body::aggregate(req.into_body()).await?.reader();
+
+            #[derive(Deserialize)]
+            struct MemberForm {
+                name : String,
+                role : Role,
+            }
+
+            let form : MemberForm = serde_urlencoded::from_reader(reader)
+                .map_err(|_| Error::BadRequest)?;
+
+            server.db.set_member(
+                workspace_id,
+                form.name.trim(),
+                form.role,
+            ).await?;
+
+            Ok(see_other(&format!("/users/{}/members.html", workspace_id)))
+        })
+    )
+}
+
+fn post_users_member_remove(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "members" / u32 / "remove.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, member_id : u32, token_id : u32, server : Server| async move {
+            // authz, owners remove anyone and members can leave
+            let (id, role) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            if role < Role::Owner && member_id != token_id {
+                return Err(Error::Unauthorized)
+            }
+            Ok((req, id, member_id, token_id, server))
+        })
+        .aand_then(|_req, workspace_id, member_id, user_id, server : Server| async move {
+            server.db.get_workspace(workspace_id, user_id).await?;
+            server.db.remove_member(workspace_id, member_id).await?;
+
+            if member_id == user_id {
+                return Ok(see_other(&format!("/users/{}/workspaces.html", user_id)))
+            }
+
+            Ok(see_other(&format!("/users/{}/members.html", workspace_id)))
+        })
+    )
+}
+
+/// the user, if they have the admin role
+async fn authorize_admin(
+    server : &Server,
+    user_id : u32,
+) -> Result<models::User, Error> {
+    let user = server.db.get_user(user_id).await?;
+    if !server.admin.is_admin(&user.name) {
+        return Err(Error::Unauthorized)
+    }
+
+    Ok(user)
+}
+
+fn get_admin_users(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "admin" / "users.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req : Request, token_id : u32, server : Server| async move {
+            // authz
+            let admin = authorize_admin(&server, token_id).await?;
+
+            let users = server.db.get_user_summaries().await?;
+            let page = server.render.admin_users(
+                &admin,
+                &users,
+                &csrf_token(&server, &req),
+            );
+
+            Ok(Response::new(page.into()))
+        })
+    )
+}
+
+/// disables the user, or enables them again
+async fn set_user_disabled(
+    server : Server,
+    admin_id : u32,
+    user_id : u32,
+    disabled : bool,
+) -> Result<Response, Error> {
+    // authz
+    authorize_admin(&server, admin_id).await?;
+
+    // admins can't lock themselves out
+    if user_id == admin_id {
+        return Err(Error::BadRequest)
+    }
+
+    server.db.set_user_disabled(user_id, disabled).await?;
+
+    Ok(see_other("/admin/users.html"))
+}
+
+fn post_admin_user_disable(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "admin" / "users" / u32 / "disable.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|_req : Request, user_id : u32, token_id : u32, server : Server| async move {
+            set_user_disabled(server, token_id, user_id, true).await
+        })
+    )
+}
+
+fn post_admin_user_enable(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "admin" / "users" / u32 / "enable.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|_req : Request, user_id : u32, token_id : u32, server : Server| async move {
+            set_user_disabled(server, token_id, user_id, false).await
+        })
+    )
+}
+
+/// answers with the feed, or 304 not modified if the reader's copy is still
+/// current
+fn feed_response(
+    req : &Request,
+    format : feed::Format,
+    feed : &feed::Feed<'_>,
+) -> Response {
+    let body = format.render(feed);
+    let etag = format!("\"{}\"", &hashing::sha256_hex(body.as_bytes())[..32]);
+    let last_modified = feed.updated.format(&HTTP_DATE_FORMAT).unwrap();
+
+    let header_str = |name| {
+        req.headers().get(name).and_then(|v| v.to_str().ok())
+    };
+
+    // if-none-match wins when both are sent
+    let not_modified = match header_str(header::IF_NONE_MATCH) {
+        Some(tags) => tags.split(',').any(|tag| {
+            let tag = tag.trim();
+            tag == "*" || tag.trim_start_matches("W/") == etag
+        }),
+        None => header_str(header::IF_MODIFIED_SINCE)
+            .and_then(|since| {
+                time::PrimitiveDateTime::parse(since, &HTTP_DATE_FORMAT).ok()
+            })
+            .map(|since| **feed.updated <= since.assume_utc())
+            .unwrap_or(false),
+    };
+
+    let res = http::response::Builder::new()
+        .header(header::ETAG, etag)
+        .header(header::LAST_MODIFIED, last_modified);
+
+    if not_modified {
+        return res
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    res.header(header::CONTENT_TYPE, format.content_type())
+        .body(body.into())
+        .unwrap()
+}
+
+/// the feed of a user's links, or the ones with the tag
+async fn users_feed(
+    server : Server,
+    req : Request,
+    user_id : u32,
+    tag : Option<String>,
+    format : feed::Format,
+) -> Result<Response, Error> {
+    let links = server.db.get_links_page(
+        user_id,
+        tag.as_deref(),
+        None,
+        None,
+        models::Sort::Newest,
+        None,
+        feed::SIZE,
+    ).await?;
+    let user = server.db.get_user(user_id).await?;
+    let changed = server.db.get_links_changed(user_id).await?;
+
+    let (title, path) = match &tag {
+        Some(tag) => (
+            format!("{}'s #{} links", user.name, tag),
+            format!("/users/{}/tags/{}/links", user.id, tag),
+        ),
+        None => (
+            format!("{}'s links", user.name),
+            format!("/users/{}/links", user.id),
+        ),
+    };
+
+    let base = format!("https://{}", server.server_name);
+    // keeps the feed token in the feed's own url
+    let query = req.uri()
+        .query()
+        .map(|q| format!("?{}", q))
+        .unwrap_or_default();
+
+    Ok(feed_response(&req, format, &feed::Feed {
+        title,
+        host :    &server.server_name,
+        url :     format!("{}{}.{}{}", base, path, format.extension(), query),
+        page :    format!("{}{}.html", base, path),
+        author :  &user.name,
+        links :   &links.links,
+        updated : changed.as_ref().unwrap_or(&user.created),
+    }))
+}
+
+/// the feed of a public or unlisted collection
+async fn collection_feed(
+    server : Server,
+    req : Request,
+    slug : String,
+    format : feed::Format,
+) -> Result<Response, Error> {
+    let collection = server.db.get_shared_collection(&slug).await?;
+    let links = server.db.get_links_page(
+        collection.user_id,
+        None,
+        Some(collection.id),
+        None,
+        models::Sort::Newest,
+        None,
+        feed::SIZE,
+    ).await?;
+    let user = server.db.get_user(collection.user_id).await?;
+    let changed = server.db.get_links_changed(collection.user_id).await?;
+
+    let base = format!("https://{}", server.server_name);
+
+    Ok(feed_response(&req, format, &feed::Feed {
+        title :   collection.name.clone(),
+        host :    &server.server_name,
+        url :     format!(
+            "{}/collections/{}/links.{}",
+            base,
+            slug,
+            format.extension(),
+        ),
+        page :    format!("{}/collections/{}/links.html", base, slug),
+        author :  &user.name,
+        links :   &links.links,
+        updated : changed.as_ref().unwrap_or(&collection.created),
+    }))
+}
+
+fn get_users_links_atom(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "links.atom"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_feed_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|req : Request, user_id, server : Server| async move {
+            users_feed(server, req, user_id, None, feed::Format::Atom).await
+        })
+    )
+}
+
+fn get_users_links_rss(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "links.rss"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_feed_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|req : Request, user_id, server : Server| async move {
+            users_feed(server, req, user_id, None, feed::Format::Rss).await
+        })
+    )
+}
+
+fn get_users_tag_links_atom(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "tags" / String / "links.atom"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_feed_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, tag : String, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, tag, server))
+        })
+        .aand_then(|req : Request, user_id, tag : String, server : Server| async move {
+            users_feed(server, req, user_id, Some(tag), feed::Format::Atom).await
+        })
+    )
+}
+
+fn get_users_tag_links_rss(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "tags" / String / "links.rss"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_feed_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, tag : String, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, tag, server))
+        })
+        .aand_then(|req : Request, user_id, tag : String, server : Server| async move {
+            users_feed(server, req, user_id, Some(tag), feed::Format::Rss).await
+        })
+    )
+}
+
+fn get_collection_links_atom(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "collections" / String / "links.atom"),
+        mux::new_handler()
+        .map_bind(server.clone())
+        .aand_then(|req : Request, slug : String, server : Server| async move {
+            collection_feed(server, req, slug, feed::Format::Atom).await
+        })
+    )
+}
+
+fn get_collection_links_rss(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "collections" / String / "links.rss"),
+        mux::new_handler()
+        .map_bind(server.clone())
+        .aand_then(|req : Request, slug : String, server : Server| async move {
+            collection_feed(server, req, slug, feed::Format::Rss).await
+        })
+    )
+}
+
+fn get_users_link_edit(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "links" / u32 / "edit.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, link_id, server))
+        })
+        .aand_then(|req, user_id, link_id, server : Server| async move {
+            let link = server.db.get_link(user_id, link_id).await?;
+            let collections = server.db.get_collections(user_id, None).await?;
+            let in_collections = server.db
+                .get_link_collection_ids(user_id, link_id)
+                .await?;
+            let user = server.db.get_user(user_id).await?;
+            let page = server.render.users_link_edit(
+                &user,
+                &link,
+                &collections,
+                &in_collections,
+                &csrf_token(&server, &req),
+            );
+
+            Ok(Response::new(page.into()))
+        })
+    )
+}
+
+fn post_users_link_edit(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "links" / u32 / "edit.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, link_id, server))
+        })
+        .aand_then(|req : Request, user_id, link_id, server : Server| async move {
+            let reader = hyper::body::aggregate(req.into_body()).await?.reader();
+
+            #[derive(Deserialize)]
+            struct EditLinkForm {
+                title :       String,
+                description : String,
+                notes :       String,
+            }
+
+            let form : EditLinkForm = serde_urlencoded::from_reader(reader)
+                .map_err(|_| Error::BadRequest)?;
+
+            // empty fields clear the value
+            fn non_empty(s : &str) -> Option<&str> {
+                Some(s.trim()).filter(|s| !s.is_empty())
+            }
+
+            server.db.update_link(
+                user_id,
+                link_id,
+                non_empty(&form.title),
+                non_empty(&form.description),
+                non_empty(&form.notes),
+            ).await?;
+
+            Ok(see_other(&format!("/users/{}/links.html", user_id)))
+        })
+    )
+}
+
+fn get_users_search(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "search.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|req : Request, user_id, server : Server| async move {
+            #[derive(Deserialize)]
+            struct Query {
+                #[serde(default)]
+                q : String,
+            }
+
+            let query : Query =
+                serde_urlencoded::from_str(req.uri().query().unwrap_or(""))
+                    .map_err(|_| Error::BadRequest)?;
+
+            let results = server.db.search_links(user_id, &query.q).await?;
+            let user = server.db.get_user(user_id).await?;
+            let page = server.render.users_search(&user, &query.q, &results);
+
+            Ok(Response::new(page.into()))
+        })
+    )
+}
+
+fn get_users_link_snapshot(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "links" / u32 / "snapshot"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, link_id, server))
+        })
+        .aand_then(|_req, user_id, link_id, server : Server| async move {
+            let snapshot = server.db.get_latest_snapshot(user_id, link_id).await?;
+
+            snapshot_response(&server, snapshot).await
+        })
+    )
+}
+
+fn get_users_link_archive(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "links" / u32 / "archive"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, link_id, server))
+        })
+        .aand_then(|_req, user_id, link_id, server : Server| async move {
+            let snapshot = server.db.get_latest_snapshot(user_id, link_id).await?;
+
+            archive_response(&server, snapshot).await
+        })
+    )
+}
+
+/// serves the snapshot's body as it was fetched
+async fn snapshot_response(
+    server : &Server,
+    snapshot : models::Snapshot,
+) -> Result<Response, Error> {
+    let digest = snapshot.digest
+        .ok_or(Error::SnapshotNotFound(snapshot.link_id))?;
+    let body = server.db.get_blob(&digest).await?;
+
+    let content_type = snapshot.content_type
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+
+    // archived pages must not be able to run scripts on our origin
+    Ok(http::response::Builder::new()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_SECURITY_POLICY, "sandbox")
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .body(body.into()).unwrap())
+}
+
+/// serves the self-contained version of the snapshot
+async fn archive_response(
+    server : &Server,
+    snapshot : models::Snapshot,
+) -> Result<Response, Error> {
+    let digest = snapshot.inlined_digest
+        .ok_or(Error::SnapshotNotFound(snapshot.link_id))?;
+    let body = server.db.get_blob(&digest).await?;
+
+    // everything the page needs is inlined, so nothing may be loaded from the
+    // network, and scripts were removed but stay disabled in case something
+    // got through
+    Ok(http::response::Builder::new()
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .header(
+            header::CONTENT_SECURITY_POLICY,
+            "sandbox; default-src 'none'; img-src data:; \
+            style-src 'unsafe-inline' data:; font-src data:; \
+            media-src data:",
+        )
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .header(header::REFERRER_POLICY, "no-referrer")
+        .body(body.into()).unwrap())
+}
+
+/// serves the reader mode version of the snapshot
+async fn reader_response(
+    server : &Server,
+    snapshot : models::Snapshot,
+) -> Result<Response, Error> {
+    let digest = snapshot.reader_digest
+        .ok_or(Error::SnapshotNotFound(snapshot.link_id))?;
+    let body = server.db.get_blob(&digest).await?;
+
+    // only images are loaded from the network, they're left as links to the
+    // original site
+    Ok(http::response::Builder::new()
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .header(
+            header::CONTENT_SECURITY_POLICY,
+            "sandbox; default-src 'none'; img-src http: https: data:; \
+            style-src 'unsafe-inline'",
+        )
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .header(header::REFERRER_POLICY, "no-referrer")
+        .body(body.into()).unwrap())
+}
+
+/// the text of the snapshot for diffing, none if it isn't text
+async fn snapshot_text(
+    server : &Server,
+    snapshot : &models::Snapshot,
+) -> Result<Option<String>, Error> {
+    // the reader text leaves out the boilerplate, which makes for a more
+    // useful diff
+    if let Some(digest) = &snapshot.text_digest {
+        let text = server.db.get_blob(digest).await?;
+        return Ok(Some(String::from_utf8_lossy(&text).into_owned()))
+    }
+
+    let digest = match &snapshot.digest {
+        Some(digest) => digest,
+        None => return Ok(None),
+    };
+
+    let content_type = snapshot.content_type.as_deref().unwrap_or("");
+    let is_html = content_type.starts_with("text/html");
+    if !is_html && !content_type.starts_with("text/") {
+        return Ok(None)
+    }
+
+    let body = server.db.get_blob(digest).await?;
+    let body = String::from_utf8_lossy(&body);
+
+    if is_html {
+        Ok(Some(html::text(&body)))
+    } else {
+        Ok(Some(body.into_owned()))
+    }
+}
+
+fn get_users_link_reader(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "links" / u32 / "reader"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, link_id, server))
+        })
+        .aand_then(|_req, user_id, link_id, server : Server| async move {
+            let snapshot = server.db.get_latest_snapshot(user_id, link_id).await?;
+
+            reader_response(&server, snapshot).await
+        })
+    )
+}
+
+fn get_users_link_history(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "links" / u32 / "history.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, link_id, server))
+        })
+        .aand_then(|req, user_id, link_id, server : Server| async move {
+            let user = server.db.get_user(user_id).await?;
+            let link = server.db.get_link(user_id, link_id).await?;
+            let snapshots = server.db.get_snapshots(user_id, link_id).await?;
+
+            let page = server.render.users_link_history(
+                &user,
+                &link,
+                &snapshots,
+                &csrf_token(&server, &req),
+            );
+
+            Ok(Response::new(page.into()))
+        })
+    )
+}
+
+/// archives the link again
+fn post_users_link_snapshots(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "links" / u32 / "snapshots"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, link_id, server))
+        })
+        .aand_then(|_req, user_id, link_id, server : Server| async move {
+            server.db.request_archive(user_id, link_id).await?;
+            server.archiver.wake();
+
+            Ok(see_other(&format!(
+                "/users/{}/links/{}/history.html",
+                user_id,
+                link_id,
+            )))
+        })
+    )
+}
+
+fn get_users_link_snapshot_version(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "links" / u32 / "snapshots" / u32),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, snapshot_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, link_id, snapshot_id, server))
+        })
+        .aand_then(|_req, user_id, link_id, snapshot_id, server : Server| async move {
+            let snapshot = server.db
+                .get_snapshot(user_id, link_id, snapshot_id)
+                .await?;
+
+            snapshot_response(&server, snapshot).await
+        })
+    )
+}
+
+fn get_users_link_snapshot_archive(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "links" / u32 / "snapshots" / u32 / "archive"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, snapshot_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, link_id, snapshot_id, server))
+        })
+        .aand_then(|_req, user_id, link_id, snapshot_id, server : Server| async move {
+            let snapshot = server.db
+                .get_snapshot(user_id, link_id, snapshot_id)
+                .await?;
+
+            archive_response(&server, snapshot).await
+        })
+    )
+}
+
+fn get_users_link_snapshot_reader(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "links" / u32 / "snapshots" / u32 / "reader"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, snapshot_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, link_id, snapshot_id, server))
+        })
+        .aand_then(|_req, user_id, link_id, snapshot_id, server : Server| async move {
+            let snapshot = server.db
+                .get_snapshot(user_id, link_id, snapshot_id)
+                .await?;
+
+            reader_response(&server, snapshot).await
+        })
+    )
+}
+
+fn get_users_link_diff(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "links" / u32 / "diff.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, link_id, server))
+        })
+        .aand_then(|req : Request, user_id, link_id, server : Server| async move {
+            #[derive(Deserialize)]
+            struct Query {
+                from : u32,
+                to :   u32,
+            }
+
+            let query : Query =
+                serde_urlencoded::from_str(req.uri().query().unwrap_or(""))
+                    .map_err(|_| Error::BadRequest)?;
+
+            let user = server.db.get_user(user_id).await?;
+            let link = server.db.get_link(user_id, link_id).await?;
+            let from = server.db.get_snapshot(user_id, link_id, query.from).await?;
+            let to = server.db.get_snapshot(user_id, link_id, query.to).await?;
+
+            let from_text = snapshot_text(&server, &from).await?;
+            let to_text = snapshot_text(&server, &to).await?;
+
+            let changes = match (&from_text, &to_text) {
+                (Some(from_text), Some(to_text)) => {
+                    Some(diff::lines(from_text, to_text))
+                },
+                _ => None,
+            };
+
+            let page = server.render.users_link_diff(
+                &user,
+                &link,
+                &from,
+                &to,
+                changes.as_deref(),
+            );
+
+            Ok(Response::new(page.into()))
+        })
+    )
+}
+
+fn get_users_trash(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "trash.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|req, user_id, server : Server| async move {
+            let links = server.db.get_deleted_links(user_id).await?;
+            let user = server.db.get_user(user_id).await?;
+            let page = server.render.users_trash(
+                &user,
+                links.as_slice(),
+                &csrf_token(&server, &req),
+            );
+
+            Ok(Response::new(page.into()))
+        })
+    )
+}
+
+fn get_users_tokens(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "users" / UserId / "tokens.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Owner)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|req, user_id, server : Server| async move {
+            let tokens = server.db.get_api_tokens(user_id).await?;
+            let feed_token = server.db.get_feed_token(user_id).await?;
+            let user = server.db.get_user(user_id).await?;
+            let page = server.render.users_tokens(
+                &user,
+                &tokens,
+                None,
+                &feed_token,
+                &csrf_token(&server, &req),
+            );
+
+            Ok(Response::new(page.into()))
+        })
+    )
+}
+
+fn post_users_tokens(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "tokens.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Owner)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|req : Request, user_id, server : Server| async move {
+            // the body is read below, which takes the request
+            let csrf = csrf_token(&server, &req);
+
+            let reader = hyper::body::aggregate(req.into_body()).await?.reader();
+
+            #[derive(Deserialize)]
+            struct PostTokensForm {
+                name : String,
+            }
+
+            let form : PostTokensForm = serde_urlencoded::from_reader(reader)
+                .map_err(|_| Error::BadRequest)?;
+
+            let name = form.name.trim();
+            if name.is_empty() {
+                return Err(Error::BadRequest)
+            }
+
+            let token = api_tokens::generate();
+            let hash = api_tokens::hash(&token);
+            server.db.insert_api_token(user_id, name, &hash).await?;
+
+            // this is the only time the token is shown
+            let tokens = server.db.get_api_tokens(user_id).await?;
+            let feed_token = server.db.get_feed_token(user_id).await?;
+            let user = server.db.get_user(user_id).await?;
+            let page = server.render.users_tokens(
+                &user,
+                &tokens,
+                Some(&token),
+                &feed_token,
+                &csrf,
+            );
+
+            Ok(Response::new(page.into()))
+        })
+    )
+}
+
+fn post_users_token_revoke(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "tokens" / u32 / "revoke.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, api_token_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Owner)
+                .await?;
+            Ok((req, id, api_token_id, server))
+        })
+        .aand_then(|_req, user_id, api_token_id, server : Server| async move {
+            server.db.revoke_api_token(user_id, api_token_id).await?;
+
+            Ok(see_other(&format!("/users/{}/tokens.html", user_id)))
+        })
+    )
+}
+
+fn post_users_feed_token(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "feed_token.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Owner)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|_req, user_id, server : Server| async move {
+            server.db.reset_feed_token(user_id).await?;
+
+            Ok(see_other(&format!("/users/{}/tokens.html", user_id)))
+        })
+    )
+}
+
+fn delete_users_link(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(DELETE / "users" / UserId / "links" / u32),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, link_id, server))
+        })
+        .aand_then(|_req, user_id, link_id, server : Server| async move {
+            server.db.delete_link(user_id, link_id).await?;
+
+            Ok(http::response::Builder::new()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::empty()).unwrap())
+        })
+    )
+}
+
+fn post_users_link_delete(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "links" / u32 / "delete.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, link_id, server))
+        })
+        .aand_then(|_req, user_id, link_id, server : Server| async move {
+            server.db.delete_link(user_id, link_id).await?;
+
+            Ok(see_other(&format!("/users/{}/links.html", user_id)))
+        })
+    )
+}
+
+fn post_users_link_restore(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "links" / u32 / "restore.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, link_id, server))
+        })
+        .aand_then(|_req, user_id, link_id, server : Server| async move {
+            server.db.restore_link(user_id, link_id).await?;
+
+            Ok(see_other(&format!("/users/{}/trash.html", user_id)))
+        })
+    )
+}
+
+fn post_users_link_purge(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "users" / UserId / "links" / u32 / "purge.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, link_id, server))
+        })
+        .aand_then(|_req, user_id, link_id, server : Server| async move {
+            server.db.purge_link(user_id, link_id).await?;
+
+            Ok(see_other(&format!("/users/{}/trash.html", user_id)))
+        })
+    )
+}
+
+fn get_login(server : Server, m : Mux) -> Mux {
+
+    m.handle(
+        route!(GET / "login.html"),
+        mux::new_handler()
+        .map_bind(server.clone())
+        .aand_then(|req : Request, server : Server| async move {
+            let session = csrf_session(&req);
+
+            if with_authn(server.clone())((req,)).await.is_ok() {
+                return Ok(see_other("/users/self/links.html"))
+            }
+
+            // the login form's csrf token needs a session to be bound to
+            let (session, cookie) = match session {
+                Some(session) => (session, None),
+                None => {
+                    let session = csrf::session();
+                    let cookie = csrf_cookie(&session);
+                    (session, Some(cookie))
+                },
+            };
+
+            let page = server.render.login(&server.csrf.token(&session));
+            let mut res = Response::new(page.into());
+
+            if let Some(cookie) = cookie {
+                res.headers_mut()
+                    .insert(header::SET_COOKIE, cookie.parse().unwrap());
+            }
+
+            Ok(res)
+        })
+    )
+}
+
+fn get_logout(_server : Server, m : Mux) -> Mux {
+
+    m.handle(
+        route!(GET / "logout.html"),
+        mux::new_handler()
+        .map(|_| {
+            let cookie = Cookie::build(COOKIE_NAME, "<logged out>")
+                .http_only(true)
+                .same_site(cookie::SameSite::Strict)
+                .path("/")
+                .finish()
+                .to_string();
+
+            http::response::Builder::new()
+                .header(header::LOCATION, "/login.html")
+                .status(StatusCode::SEE_OTHER)
+                .header(header::SET_COOKIE, cookie)
+                .body("redirecting".into()).unwrap()
+        })
+    )
+}
+
+fn post_login(server : Server, m : Mux) -> Mux {
+    #[derive(Deserialize)]
+    struct Req {
+        username : String,
+        password : String,
+    }
+
+    struct Res {
+        token : String,
+    }
+
+    impl From<Res> for Response {
+        fn from(res : Res) -> Self {
+            http::response::Builder::new()
+                .header(header::LOCATION, "/users/self/links.html")
+                .header(header::SET_COOKIE, token_cookie(&res.token))
+                .status(StatusCode::SEE_OTHER)
+                .body("redirecting".into()).unwrap()
+        }
+    }
+
+    m.handle(
+        route!(POST / "login.html"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req : Request, server : Server| async move {
+            let ip = client_ip(&req);
+            let reader = hyper::body::aggregate(req.into_body()).await?.reader();
+
+            let form : Req = serde_urlencoded::from_reader(reader)
+                .map_err(|_| Error::BadRequest)?;
+
+            let token =
+                login(&server, &ip, &form.username, &form.password).await?;
+
+            Ok(Res{token}.into())
+        })
+    )
+}
+
+/// whether a failed login is authn not answering, rather than turning the
+/// password down. authn's client error doesn't tell them apart, but failing
+/// to connect or to read its answer is a hyper or io error, which is in the
+/// debug output.
+fn authn_unreachable(error : &str) -> bool {
+    error.contains("hyper::Error") || error.contains("Os {")
+}
+
+/// logs in through authn, creating the user if this is their first login and
+/// they may register, and returns the session token. failures are throttled
+/// by the client's ip.
+async fn login(
+    server : &Server,
+    ip : &str,
+    username : &str,
+    password : &str,
+) -> Result<String, Error> {
+    let limiter = &server.limiter;
+    let key = ratelimit::ip_key(ip);
+
+    // authn isn't asked at all while locked out, so guesses can't be checked
+    if let Some(wait) = limiter.locked(&key) {
+        return Err(Error::TooManyAttempts(wait.as_secs() + 1))
+    }
+
+    let token = server.authn.login(
+        username,
+        password,
+        Duration::from_secs(60 * 60 * 24 * 7),
+    ).await.map_err(|err| {
+        let error = format!("{:?}", err);
+
+        if authn_unreachable(&error) {
+            log!(Error, "authn unreachable", user = username, error = error);
+            return Error::Authn(err)
+        }
+
+        log!(Info, "login failed", user = username, ip = ip, error = error);
+        limiter.failure(&key);
+        Error::FailedLogin
+    })?;
+
+    let may_register = server.admin.may_register(username);
+    server.db.upsert_user(username, may_register).await?;
+
+    Ok(token)
+}
+
+fn token_cookie(token : &str) -> String {
+    Cookie::build(COOKIE_NAME, token)
+        .http_only(true)
+        .same_site(cookie::SameSite::Strict)
+        .path("/")
+        .finish()
+        .to_string()
+}
+
+fn csrf_cookie(session : &str) -> String {
+    Cookie::build(csrf::COOKIE_NAME, session)
+        .http_only(true)
+        .same_site(cookie::SameSite::Strict)
+        .path("/")
+        .finish()
+        .to_string()
+}
+
+/// redirects the client to location, used after form posts
+fn see_other(location : &str) -> Response {
+    http::response::Builder::new()
+        .header(header::LOCATION, location)
+        .status(StatusCode::SEE_OTHER)
+        .body("redirecting".into()).unwrap()
+}
+
+/// the status code an error is reported with, shared by the html and json
+/// routes
+fn error_status(err : &Error) -> StatusCode {
+    use http::StatusCode as S;
+    use Error::*;
+
+    match err {
+        InvalidUrl(_) | InvalidTag(_) | BadRequest => S::BAD_REQUEST,
+        DuplicateUrl(_, _) | DuplicateName(_) | LastOwner => S::CONFLICT,
+        UserNameNotFound(_)
+        | UserIdNotFound(_)
+        | LinkIdNotFound(_)
+        | ApiTokenIdNotFound(_)
+        | SnapshotNotFound(_)
+        | CollectionIdNotFound(_)
+        | CollectionSlugNotFound(_)
+        | WorkspaceIdNotFound(_)
+        | RouteNotFound => S::NOT_FOUND,
+        FailedLogin => S::UNAUTHORIZED,
+        Unauthorized
+        | UserDisabled(_)
+        | RegistrationClosed(_)
+        | CsrfMismatch => S::FORBIDDEN,
+        UnsupportedMediaType => S::UNSUPPORTED_MEDIA_TYPE,
+        PayloadTooLarge => S::PAYLOAD_TOO_LARGE,
+        TooManyAttempts(_) => S::TOO_MANY_REQUESTS,
+        _ => S::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// the message shown to clients, internal details are not exposed
+fn error_message(err : &Error) -> String {
+    use Error::*;
+
+    match err {
+        InvalidUrl(s) => format!("invalid url: {}", s),
+        DuplicateUrl(url, id) => {
+            format!("duplicate url: already saved as link {}, {}", id, url)
+        },
+        DuplicateName(s) => format!("name is taken: {}", s),
+        InvalidTag(s) => format!("invalid tag: {}", s),
+        LinkIdNotFound(id) => format!("link not found: {}", id),
+        ApiTokenIdNotFound(id) => format!("api token not found: {}", id),
+        SnapshotNotFound(id) => format!("no snapshot of link: {}", id),
+        CollectionIdNotFound(id) => format!("collection not found: {}", id),
+        CollectionSlugNotFound(_) => "collection not found".to_string(),
+        WorkspaceIdNotFound(id) => format!("workspace not found: {}", id),
+        UserNameNotFound(name) => format!("user not found: {}", name),
+        UserIdNotFound(id) => format!("user not found: {}", id),
+        LastOwner => "a workspace needs an owner".to_string(),
+        RouteNotFound => "route not found".to_string(),
+        FailedLogin => "login failed".to_string(),
+        UserDisabled(_) => "this account is disabled".to_string(),
+        RegistrationClosed(_) => "registration is closed".to_string(),
+        TooManyAttempts(secs) => {
+            format!("too many failed logins, try again in {} seconds", secs)
+        },
+        CsrfMismatch => {
+            "the form expired, go back and reload the page".to_string()
+        },
+        UnsupportedMediaType => {
+            "expected a body with content-type application/json".to_string()
+        },
+        PayloadTooLarge => {
+            format!("the body is bigger than {} bytes", MAX_BODY_SIZE)
+        },
+        Unauthorized => "unauthorized".to_string(),
+        BadRequest => "bad request".to_string(),
+        _ => "internal server error".to_string(),
+    }
+}
+
+/// server errors are logged as errors, the rest are the client's mistakes
+fn log_error(err : &Error, status : StatusCode) {
+    let error = format!("{:?}", err);
+
+    if status.is_server_error() {
+        log!(Error, "handler failed", code = err.code(), error = error);
+    } else {
+        log!(Debug, "handler failed", code = err.code(), error = error);
+    }
+}
 
-   http::response::Builder::new()
-       .status(status)
+fn render_error(err : Error) -> Response {
+    let status = error_status(&err);
+    log_error(&err, status);
+
+    let mut res = http::response::Builder::new().status(status);
+
+    if let Error::TooManyAttempts(secs) = &err {
+        res = res.header(header::RETRY_AFTER, *secs);
+    }
+
+    let body = match err {
+        Error::FailedLogin => {
+            include_str!("../ui/failed-login.html").to_string()
+        },
+        _ => error_message(&err),
+    };
+
+   res
        .body(body.into())
        .unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn link_urls_are_http() {
+        for url in &["https://ear7h.net/a?b=c", "http://ear7h.net"] {
+            assert!(parse_link_url(url).is_ok(), "{}", url);
+        }
+
+        for url in &[
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            "data:text/html,<script>alert(1)</script>",
+            "place:sort=8",
+            "ear7h.net/a",
+        ] {
+            assert!(
+                matches!(parse_link_url(url), Err(Error::InvalidUrl(_))),
+                "{}",
+                url,
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn body_size_is_capped() {
+        let body = vec![b'a'; MAX_BODY_SIZE];
+        assert_eq!(read_body(body.into()).await.unwrap().len(), MAX_BODY_SIZE);
+
+        let body = vec![b'a'; MAX_BODY_SIZE + 1];
+        assert!(matches!(
+            read_body(body.into()).await,
+            Err(Error::PayloadTooLarge),
+        ));
+
+        // streamed bodies don't say how big they are up front
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            for _ in 0..17 {
+                let chunk = vec![b'a'; 1024 * 1024];
+                if sender.send_data(chunk.into()).await.is_err() {
+                    break
+                }
+            }
+        });
+        assert!(matches!(
+            read_body(body).await,
+            Err(Error::PayloadTooLarge),
+        ));
+    }
+
+    #[test]
+    fn forwarded_for_only_from_proxies() {
+        let req = |proxy| {
+            let mut req = http::Request::builder()
+                .header("x-forwarded-for", "6.6.6.6, 10.0.0.2")
+                .body(Body::empty())
+                .unwrap();
+            req.extensions_mut().insert(ClientAddr {
+                peer : "127.0.0.1:4000".parse().unwrap(),
+                proxy,
+            });
+            req
+        };
+
+        assert_eq!(client_ip(&req(true)), "10.0.0.2");
+        assert_eq!(client_ip(&req(false)), "127.0.0.1");
+    }
+
+    #[test]
+    fn unreachable_authn_is_not_a_failed_login() {
+        let io = std::io::Error::from_raw_os_error(2);
+        assert!(authn_unreachable(&format!("{:?}", io)));
+        assert!(!authn_unreachable("Error"));
+    }
+}
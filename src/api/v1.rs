@@ -0,0 +1,384 @@
+//! the json api, mirrors the html routes under /api/v1/
+
+use hyper::Body;
+use http::{header, StatusCode};
+use http_mux::{route, mux};
+use plumb::PipeExt;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    client_ip,
+    error_message,
+    error_status,
+    log_error,
+    login,
+    parse_link_url,
+    read_body,
+    token_cookie,
+    with_authn,
+    with_csrf,
+    Mux,
+    Request,
+    Response,
+    Server,
+    UserId,
+};
+use crate::error::Error;
+use crate::models::Role;
+use crate::{canonical, models};
+
+/// the largest page of links a client may ask for
+const MAX_PAGE_SIZE : u32 = 500;
+
+fn json_response<T : Serialize>(status : StatusCode, value : &T) -> Response {
+    http::response::Builder::new()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_vec(value).unwrap().into())
+        .unwrap()
+}
+
+fn render_json_error(err : Error) -> Response {
+    let status = error_status(&err);
+    log_error(&err, status);
+
+    #[derive(Serialize)]
+    struct Inner {
+        code :    &'static str,
+        message : String,
+    }
+
+    #[derive(Serialize)]
+    struct Res {
+        error : Inner,
+    }
+
+    let mut res = json_response(status, &Res {
+        error : Inner {
+            code :    err.code(),
+            message : error_message(&err),
+        },
+    });
+
+    if let Error::TooManyAttempts(secs) = &err {
+        res.headers_mut().insert(header::RETRY_AFTER, (*secs).into());
+    }
+
+    res
+}
+
+/// reports handler errors as json instead of the html error pages
+fn json_errors(res : Result<Response, Error>) -> Result<Response, Error> {
+    Ok(res.unwrap_or_else(render_json_error))
+}
+
+/// the body as json. other content types are refused, since browsers only
+/// send json to other sites after asking them, while forms posted from
+/// anywhere carry the auth cookie.
+async fn read_json<T>(req : Request) -> Result<T, Error>
+where
+    T : serde::de::DeserializeOwned,
+{
+    let json = req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|ct| ct.to_str().ok())
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().eq_ignore_ascii_case("application/json"))
+        .unwrap_or(false);
+    if !json {
+        return Err(Error::UnsupportedMediaType)
+    }
+
+    let body = read_body(req.into_body()).await?;
+
+    serde_json::from_slice(&body).map_err(|_| Error::BadRequest)
+}
+
+/// sets the link's tags to exactly the given ones
+async fn set_tags(
+    server : &Server,
+    user_id : u32,
+    link_id : u32,
+    tags : &[String],
+) -> Result<(), Error> {
+    let tags = tags
+        .iter()
+        .map(|tag| {
+            Some(tag.to_lowercase())
+                .filter(|tag| models::valid_tag(tag))
+                .ok_or_else(|| Error::InvalidTag(tag.clone()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let link = server.db.get_link(user_id, link_id).await?;
+
+    for old in link.tags.0.iter().filter(|old| !tags.contains(old)) {
+        server.db.remove_tag(user_id, link_id, old).await?;
+    }
+
+    for tag in tags.iter() {
+        server.db.add_tag(user_id, link_id, tag).await?;
+    }
+
+    Ok(())
+}
+
+pub(super) fn get_user(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "api" / "v1" / "users" / UserId),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|_req, user_id, server : Server| async move {
+            let user = server.db.get_user(user_id).await?;
+
+            Ok(json_response(StatusCode::OK, &user))
+        })
+        .tuple()
+        .seq(json_errors)
+    )
+}
+
+pub(super) fn get_links(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "api" / "v1" / "users" / UserId / "links"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|req : Request, user_id, server : Server| async move {
+            #[derive(Deserialize)]
+            struct Query {
+                tag :    Option<String>,
+                health : Option<models::Health>,
+                #[serde(default)]
+                sort :   models::Sort,
+                cursor : Option<String>,
+                limit :  Option<u32>,
+            }
+
+            let query : Query =
+                serde_urlencoded::from_str(req.uri().query().unwrap_or(""))
+                    .map_err(|_| Error::BadRequest)?;
+
+            let limit = query.limit.unwrap_or(100).clamp(1, MAX_PAGE_SIZE);
+
+            let page = server.db.get_links_page(
+                user_id,
+                query.tag.as_deref(),
+                None,
+                query.health,
+                query.sort,
+                query.cursor.as_deref(),
+                limit,
+            ).await?;
+
+            Ok(json_response(StatusCode::OK, &page))
+        })
+        .tuple()
+        .seq(json_errors)
+    )
+}
+
+pub(super) fn post_links(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(POST / "api" / "v1" / "users" / UserId / "links"),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, server))
+        })
+        .aand_then(|req : Request, user_id, server : Server| async move {
+            #[derive(Deserialize)]
+            struct Req {
+                url :         String,
+                #[serde(default)]
+                tags :        Vec<String>,
+                title :       Option<String>,
+                description : Option<String>,
+                notes :       Option<String>,
+            }
+
+            let body : Req = read_json(req).await?;
+
+            let u = parse_link_url(&body.url)?;
+
+            let canonical = canonical::canonicalize(&u, &server.canonical);
+            let link_id = server.db
+                .insert_link(user_id, u.as_str(), &canonical, None)
+                .await?;
+
+            server.db.update_link(
+                user_id,
+                link_id,
+                body.title.as_deref(),
+                body.description.as_deref(),
+                body.notes.as_deref(),
+            ).await?;
+
+            set_tags(&server, user_id, link_id, &body.tags).await?;
+            server.archiver.wake();
+
+            let link = server.db.get_link(user_id, link_id).await?;
+
+            Ok(json_response(StatusCode::CREATED, &link))
+        })
+        .tuple()
+        .seq(json_errors)
+    )
+}
+
+pub(super) fn get_link(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(GET / "api" / "v1" / "users" / UserId / "links" / u32),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Viewer)
+                .await?;
+            Ok((req, id, link_id, server))
+        })
+        .aand_then(|_req, user_id, link_id, server : Server| async move {
+            let link = server.db.get_link(user_id, link_id).await?;
+
+            Ok(json_response(StatusCode::OK, &link))
+        })
+        .tuple()
+        .seq(json_errors)
+    )
+}
+
+pub(super) fn put_link(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(PUT / "api" / "v1" / "users" / UserId / "links" / u32),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, link_id, server))
+        })
+        .aand_then(|req : Request, user_id, link_id, server : Server| async move {
+            // missing fields are cleared, except for tags which are left
+            // untouched
+            #[derive(Deserialize)]
+            struct Req {
+                tags :        Option<Vec<String>>,
+                title :       Option<String>,
+                description : Option<String>,
+                notes :       Option<String>,
+            }
+
+            let body : Req = read_json(req).await?;
+
+            server.db.update_link(
+                user_id,
+                link_id,
+                body.title.as_deref(),
+                body.description.as_deref(),
+                body.notes.as_deref(),
+            ).await?;
+
+            if let Some(tags) = body.tags {
+                set_tags(&server, user_id, link_id, &tags).await?;
+            }
+
+            let link = server.db.get_link(user_id, link_id).await?;
+
+            Ok(json_response(StatusCode::OK, &link))
+        })
+        .tuple()
+        .seq(json_errors)
+    )
+}
+
+pub(super) fn delete_link(server : Server, m : Mux) -> Mux {
+    m.handle(
+        route!(DELETE / "api" / "v1" / "users" / UserId / "links" / u32),
+        mux::new_handler()
+        .map_tuple().aand_then(with_authn(server.clone()))
+        .map_tuple().aand_then(with_csrf(server.clone()))
+        .map_bind(server.clone())
+        .aand_then(|req, url_id : UserId, link_id : u32, token_id : u32, server : Server| async move {
+            // authz
+            let (id, _) = url_id
+                .authorize(&server, token_id, Role::Editor)
+                .await?;
+            Ok((req, id, link_id, server))
+        })
+        .aand_then(|_req, user_id, link_id, server : Server| async move {
+            server.db.delete_link(user_id, link_id).await?;
+
+            Ok(http::response::Builder::new()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::empty()).unwrap())
+        })
+        .tuple()
+        .seq(json_errors)
+    )
+}
+
+pub(super) fn post_login(server : Server, m : Mux) -> Mux {
+    #[derive(Deserialize)]
+    struct Req {
+        username : String,
+        password : String,
+    }
+
+    #[derive(Serialize)]
+    struct Res {
+        token : String,
+    }
+
+    m.handle(
+        route!(POST / "api" / "v1" / "login"),
+        mux::new_handler()
+        .map_bind(server.clone())
+        .aand_then(|req : Request, server : Server| async move {
+            let ip = client_ip(&req);
+            let body : Req = read_json(req).await?;
+
+            let token =
+                login(&server, &ip, &body.username, &body.password).await?;
+
+            let mut res = json_response(StatusCode::OK, &Res {
+                token : token.clone(),
+            });
+
+            res.headers_mut().insert(
+                header::SET_COOKIE,
+                token_cookie(&token).parse().unwrap(),
+            );
+
+            Ok(res)
+        })
+        .tuple()
+        .seq(json_errors)
+    )
+}
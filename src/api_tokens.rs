@@ -0,0 +1,21 @@
+use rand::RngCore;
+
+use crate::hashing;
+
+/// prefix for personal api tokens so they're easy to recognize, e.g. by
+/// secret scanners
+const PREFIX : &str = "la_";
+
+/// generates a new random token, only its hash should be stored
+pub fn generate() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    format!("{}{}", PREFIX, hashing::hex(&bytes))
+}
+
+/// the hash stored in the database. tokens have enough entropy that they
+/// don't need to be salted.
+pub fn hash(token : &str) -> String {
+    hashing::sha256_hex(token.as_bytes())
+}
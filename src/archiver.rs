@@ -0,0 +1,588 @@
+//! the archiver fetches newly saved links in the background and stores a
+//! snapshot of the page
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use hyper::body::HttpBody;
+use hyper::client::HttpConnector;
+use hyper::{Body, Client};
+use hyper_rustls::HttpsConnector;
+use serde::Deserialize;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use crate::api::Server;
+use crate::netguard::{self, Guard};
+use crate::{hashing, inline, metadata, models, reader, Error, Result};
+
+/// links archived per database query
+const BATCH_SIZE : u32 = 16;
+/// how long to wait for new links before checking the database again
+const IDLE_INTERVAL : Duration = Duration::from_secs(60);
+const FETCH_TIMEOUT : Duration = Duration::from_secs(30);
+const MAX_REDIRECTS : usize = 10;
+/// bodies larger than this aren't archived
+const MAX_BODY_SIZE : usize = 16 * 1024 * 1024;
+/// assets fetched per inlined page
+const MAX_ASSETS : usize = 100;
+/// the total size of the assets inlined into a page
+const MAX_ASSETS_SIZE : usize = 32 * 1024 * 1024;
+/// how long fetching the assets of a page may take altogether, so a slow
+/// site doesn't hold the archiver up for MAX_ASSETS fetch timeouts
+const INLINE_TIMEOUT : Duration = Duration::from_secs(120);
+pub(crate) const USER_AGENT : &str =
+    concat!("link-archive/", env!("CARGO_PKG_VERSION"));
+
+/// a fetched page
+#[derive(Debug)]
+pub struct Fetched {
+    /// the url after following redirects
+    pub url :          String,
+    pub status :       u16,
+    pub headers :      Vec<(String, String)>,
+    pub content_type : Option<String>,
+    pub body :         Vec<u8>,
+}
+
+/// the content address of a blob
+pub fn digest(data : &[u8]) -> String {
+    format!("sha256:{}", hashing::sha256_hex(data))
+}
+
+impl Fetched {
+    fn is_html(&self) -> bool {
+        (200..300).contains(&self.status)
+            && self.content_type
+                .as_deref()
+                .map(|ct| ct.starts_with("text/html"))
+                .unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    /// links are archived again once their latest snapshot is this many days
+    /// old, never if it's unset
+    pub rearchive_days : Option<u32>,
+}
+
+/// what's made from an html page besides its snapshot
+#[derive(Debug, Default)]
+pub struct Processed {
+    /// the self-contained version of the page
+    pub inlined : Option<String>,
+    pub reader :  Option<reader::Article>,
+}
+
+pub struct Archiver {
+    config : Config,
+    guard :  Guard,
+    client : Client<HttpsConnector<HttpConnector<netguard::Resolver>>>,
+    wake :   Notify,
+}
+
+impl Archiver {
+    pub fn new(config : Config, guard : Guard) -> Self {
+        Self {
+            config,
+            client : Client::builder().build(guard.connector()),
+            guard,
+            wake :   Notify::new(),
+        }
+    }
+
+    /// snapshots fetched before this are due to be archived again
+    fn stale_before(&self) -> Option<models::Time> {
+        self.config.rearchive_days.map(|days| {
+            let age = time::Duration::days(days.into());
+            (time::OffsetDateTime::now_utc() - age).into()
+        })
+    }
+
+    /// lets the archiver know new links were saved
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+
+    /// fetches the url, following redirects
+    pub async fn fetch(&self, url : &str) -> Result<Fetched> {
+        tokio::time::timeout(FETCH_TIMEOUT, self.fetch_inner(url))
+            .await
+            .map_err(|_| Error::FetchFailed(format!("timed out: {}", url)))?
+    }
+
+    /// fetches the url, giving up at the deadline if it comes first
+    async fn fetch_before(
+        &self,
+        url : &str,
+        deadline : Instant,
+    ) -> Result<Fetched> {
+        tokio::time::timeout_at(deadline, self.fetch(url))
+            .await
+            .map_err(|_| Error::FetchFailed(format!("timed out: {}", url)))?
+    }
+
+    async fn fetch_inner(&self, url : &str) -> Result<Fetched> {
+        let mut url = url::Url::parse(url)
+            .map_err(|_| Error::InvalidUrl(url.to_string()))?;
+
+        for _ in 0..=MAX_REDIRECTS {
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(Error::FetchFailed(format!(
+                    "unsupported scheme: {}",
+                    url
+                )))
+            }
+
+            self.guard.check_url(&url)?;
+
+            let req = http::Request::get(url.as_str())
+                .header(http::header::USER_AGENT, USER_AGENT)
+                .body(Body::empty())
+                .map_err(|_| Error::InvalidUrl(url.to_string()))?;
+
+            let res = self.client.request(req).await?;
+
+            let location = res
+                .headers()
+                .get(http::header::LOCATION)
+                .and_then(|loc| loc.to_str().ok());
+
+            if res.status().is_redirection() {
+                if let Some(location) = location {
+                    url = url.join(location)
+                        .map_err(|_| Error::InvalidUrl(location.to_string()))?;
+                    continue
+                }
+            }
+
+            let status = res.status().as_u16();
+            let headers = res
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect();
+            let content_type = res
+                .headers()
+                .get(http::header::CONTENT_TYPE)
+                .and_then(|ct| ct.to_str().ok())
+                .map(String::from);
+
+            let mut body = res.into_body();
+            let mut data = Vec::new();
+            while let Some(chunk) = body.data().await {
+                data.extend_from_slice(&chunk?);
+
+                if data.len() > MAX_BODY_SIZE {
+                    return Err(Error::FetchFailed(format!(
+                        "body too large: {}",
+                        url
+                    )))
+                }
+            }
+
+            return Ok(Fetched {
+                url : url.to_string(),
+                status,
+                headers,
+                content_type,
+                body : data,
+            })
+        }
+
+        Err(Error::FetchFailed(format!("too many redirects: {}", url)))
+    }
+
+    /// makes the other versions of an html page
+    pub async fn process(&self, page : &Fetched) -> Processed {
+        let inlined = self.inline(page).await.map_err(|err| {
+            log!(Warn, "archiver failed", error = format!("{:?}", err));
+        }).ok();
+
+        let reader = url::Url::parse(&page.url)
+            .ok()
+            .map(|base| reader::extract(&page.body, &base));
+
+        Processed {
+            inlined,
+            reader,
+        }
+    }
+
+    /// fetches the assets of an html page and rewrites it into a single
+    /// self-contained document. assets which fail to fetch or go over the
+    /// limits are left as absolute urls.
+    pub async fn inline(&self, page : &Fetched) -> Result<String> {
+        self.inline_until(page, Instant::now() + INLINE_TIMEOUT).await
+    }
+
+    /// inlines the page with the assets fetched before the deadline
+    async fn inline_until(
+        &self,
+        page : &Fetched,
+        deadline : Instant,
+    ) -> Result<String> {
+        let base = url::Url::parse(&page.url)
+            .map_err(|_| Error::InvalidUrl(page.url.clone()))?;
+        let html = String::from_utf8_lossy(&page.body);
+
+        let assets = inline::html_assets(&html, &base);
+
+        let mut fetched = 0;
+        let mut size = 0;
+
+        let mut raw_stylesheets = Vec::new();
+        let mut resources = assets.resources;
+
+        for url in assets.stylesheets.into_iter().take(MAX_ASSETS) {
+            if Instant::now() >= deadline {
+                break
+            }
+            fetched += 1;
+
+            let css = match self.fetch_before(url.as_str(), deadline).await {
+                Ok(css) if (200..300).contains(&css.status) => css,
+                Ok(_) => continue,
+                Err(err) => {
+                    log!(Warn, "archiver failed", error = format!("{:?}", err));
+                    continue
+                },
+            };
+
+            size += css.body.len();
+            if size > MAX_ASSETS_SIZE {
+                break
+            }
+
+            // urls in stylesheets are relative to the stylesheet, after
+            // redirects
+            let css_base = url::Url::parse(&css.url)
+                .unwrap_or_else(|_| url.clone());
+            let text = String::from_utf8_lossy(&css.body).into_owned();
+
+            resources.extend(inline::css_urls(&text, &css_base));
+            raw_stylesheets.push((url.to_string(), css_base, text));
+        }
+
+        resources.sort();
+        resources.dedup();
+
+        let mut data = HashMap::new();
+        for url in resources {
+            if fetched >= MAX_ASSETS
+                || size > MAX_ASSETS_SIZE
+                || Instant::now() >= deadline
+            {
+                break
+            }
+            fetched += 1;
+
+            let resource = match self.fetch_before(url.as_str(), deadline)
+                .await
+            {
+                Ok(resource) if (200..300).contains(&resource.status) => {
+                    resource
+                },
+                Ok(_) => continue,
+                Err(err) => {
+                    log!(Warn, "archiver failed", error = format!("{:?}", err));
+                    continue
+                },
+            };
+
+            size += resource.body.len();
+            if size > MAX_ASSETS_SIZE {
+                break
+            }
+
+            let content_type = resource
+                .content_type
+                .as_deref()
+                .unwrap_or("application/octet-stream");
+
+            data.insert(
+                url.to_string(),
+                inline::data_uri(content_type, &resource.body),
+            );
+        }
+
+        let stylesheets = raw_stylesheets
+            .into_iter()
+            .map(|(key, css_base, css)| {
+                (key, inline::rewrite_css(&css, &css_base, &data))
+            })
+            .collect();
+
+        Ok(inline::rewrite_html(&html, &base, &stylesheets, &data))
+    }
+}
+
+/// stores the metadata of html pages on their link
+async fn update_metadata(
+    server : &Server,
+    link_id : u32,
+    page : &Fetched,
+) -> Result<()> {
+    if !page.is_html() {
+        return Ok(())
+    }
+
+    let base = url::Url::parse(&page.url)
+        .map_err(|_| Error::InvalidUrl(page.url.clone()))?;
+    let meta = metadata::extract(&page.body, &base);
+
+    server.db.update_metadata(link_id, &meta).await
+}
+
+/// archives links that don't have a snapshot yet or are due to be archived
+/// again, forever. meant to be spawned when the server starts.
+pub async fn run(server : Server) {
+    loop {
+        let stale_before = server.archiver.stale_before();
+        let links = server.db
+            .get_links_to_archive(stale_before.as_ref(), BATCH_SIZE)
+            .await;
+
+        let links = match links {
+            Ok(links) => links,
+            Err(err) => {
+                log!(Warn, "archiver failed", error = format!("{:?}", err));
+                tokio::time::sleep(IDLE_INTERVAL).await;
+                continue
+            },
+        };
+
+        if links.is_empty() {
+            // also check periodically in case links were added without
+            // waking the archiver up
+            let _ = tokio::time::timeout(
+                IDLE_INTERVAL,
+                server.archiver.wake.notified(),
+            )
+            .await;
+
+            continue
+        }
+
+        for (link_id, url) in links {
+            // failures are stored too, so the link isn't retried forever
+            let fetched = server.archiver.fetch(&url).await.map_err(|err| {
+                log!(
+                    Info,
+                    "archive fetch failed",
+                    link_id = link_id,
+                    url = url,
+                    error = format!("{:?}", err),
+                );
+                format!("{:?}", err)
+            });
+
+            let processed = match &fetched {
+                Ok(page) if page.is_html() => {
+                    server.archiver.process(page).await
+                },
+                _ => Processed::default(),
+            };
+
+            if let Ok(page) = &fetched {
+                if let Err(err) = update_metadata(&server, link_id, page).await {
+                    log!(Warn, "archiver failed", error = format!("{:?}", err));
+                }
+            }
+
+            let res = server.db
+                .insert_snapshot(link_id, &url, fetched, processed)
+                .await;
+            if let Err(err) = res {
+                // the link is still due, so it would be fetched again right
+                // away
+                log!(Warn, "archiver failed", error = format!("{:?}", err));
+                tokio::time::sleep(IDLE_INTERVAL).await;
+                break
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Request, Response, Server};
+
+    use super::*;
+
+    fn redirect(location : &str) -> http::Result<Response<Body>> {
+        Response::builder()
+            .status(302)
+            .header(http::header::LOCATION, location)
+            .body(Body::empty())
+    }
+
+    async fn route(
+        req : Request<Body>,
+    ) -> std::result::Result<Response<Body>, Infallible> {
+        let res = match req.uri().path() {
+            "/page" => Response::builder()
+                .header(http::header::CONTENT_TYPE, "text/html; charset=utf-8")
+                .body("<html><head><title>page</title></head></html>".into()),
+            "/redirect/1" => redirect("/redirect/2"),
+            "/redirect/2" => redirect("../page"),
+            "/loop" => redirect("/loop"),
+            "/escape" => redirect("http://127.0.0.2/page"),
+            "/big" => Response::builder()
+                .header(http::header::CONTENT_TYPE, "text/html")
+                .body(vec![b'a'; MAX_BODY_SIZE + 1].into()),
+            "/slow.css" => {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Response::builder()
+                    .header(http::header::CONTENT_TYPE, "text/css")
+                    .body("body {}".into())
+            },
+            "/data.json" => Response::builder()
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body("{}".into()),
+            _ => Response::builder().status(404).body(Body::empty()),
+        };
+
+        Ok(res.unwrap())
+    }
+
+    /// serves the routes above on a free port
+    fn serve() -> SocketAddr {
+        let make_service = make_service_fn(|_| async {
+            Ok::<_, Infallible>(service_fn(route))
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        addr
+    }
+
+    fn archiver() -> Archiver {
+        let guard = Guard::new(netguard::Config {
+            allow : vec![[127, 0, 0, 1].into()],
+        });
+
+        Archiver::new(Config::default(), guard)
+    }
+
+    #[tokio::test]
+    async fn fetch_html() {
+        let addr = serve();
+        let page = archiver()
+            .fetch(&format!("http://{}/page", addr))
+            .await
+            .unwrap();
+
+        assert_eq!(page.status, 200);
+        assert!(page.is_html());
+        assert_eq!(page.url, format!("http://{}/page", addr));
+        assert_eq!(
+            page.body,
+            b"<html><head><title>page</title></head></html>",
+        );
+    }
+
+    #[tokio::test]
+    async fn fetch_follows_redirects() {
+        let addr = serve();
+        let page = archiver()
+            .fetch(&format!("http://{}/redirect/1", addr))
+            .await
+            .unwrap();
+
+        assert_eq!(page.status, 200);
+        assert_eq!(page.url, format!("http://{}/page", addr));
+    }
+
+    #[tokio::test]
+    async fn fetch_too_many_redirects() {
+        let addr = serve();
+        let res = archiver().fetch(&format!("http://{}/loop", addr)).await;
+
+        assert!(matches!(
+            res,
+            Err(Error::FetchFailed(msg))
+                if msg.starts_with("too many redirects")
+        ));
+    }
+
+    #[tokio::test]
+    async fn fetch_body_too_large() {
+        let addr = serve();
+        let res = archiver().fetch(&format!("http://{}/big", addr)).await;
+
+        assert!(matches!(
+            res,
+            Err(Error::FetchFailed(msg)) if msg.starts_with("body too large")
+        ));
+    }
+
+    #[tokio::test]
+    async fn fetch_content_type() {
+        let addr = serve();
+        let page = archiver()
+            .fetch(&format!("http://{}/data.json", addr))
+            .await
+            .unwrap();
+
+        assert_eq!(page.status, 200);
+        assert_eq!(page.content_type.as_deref(), Some("application/json"));
+        assert!(!page.is_html());
+    }
+
+    #[tokio::test]
+    async fn fetch_refuses_private_addresses() {
+        let addr = serve();
+
+        let public = Archiver::new(Config::default(), Guard::default());
+        let res = public.fetch(&format!("http://{}/page", addr)).await;
+        assert!(matches!(res, Err(Error::FetchFailed(_))));
+
+        let res = public
+            .fetch(&format!("http://localhost:{}/page", addr.port()))
+            .await;
+        assert!(matches!(res, Err(Error::Hyper(_))));
+
+        // every hop is checked, not just the first
+        let res = archiver().fetch(&format!("http://{}/escape", addr)).await;
+        assert!(matches!(
+            res,
+            Err(Error::FetchFailed(msg))
+                if msg.starts_with("address not allowed")
+        ));
+    }
+
+    #[tokio::test]
+    async fn inline_stops_at_deadline() {
+        let addr = serve();
+        let html = (0..10)
+            .map(|i| {
+                format!(r#"<link rel="stylesheet" href="/slow.css?{}">"#, i)
+            })
+            .collect::<String>();
+        let page = Fetched {
+            url :          format!("http://{}/page", addr),
+            status :       200,
+            headers :      Vec::new(),
+            content_type : Some("text/html".to_string()),
+            body :         html.into_bytes(),
+        };
+
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(200);
+        let inlined = archiver().inline_until(&page, deadline).await.unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(inlined.contains(&format!("http://{}/slow.css?9", addr)));
+    }
+}
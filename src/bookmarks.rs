@@ -0,0 +1,243 @@
+//! the netscape bookmark file format, which every browser can import and
+//! export. it's html in spirit only, so it's parsed by hand.
+
+use serde::Serialize;
+
+use crate::html::{escape, find_ignore_case, unescape};
+use crate::models;
+
+#[derive(Debug)]
+pub struct Bookmark {
+    pub url :         String,
+    pub title :       Option<String>,
+    pub description : Option<String>,
+    /// seconds since the unix epoch
+    pub add_date :    Option<i64>,
+    pub tags :        Vec<String>,
+}
+
+/// the outcome of importing a bookmark file
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub new :        u32,
+    pub duplicate :  u32,
+    /// the urls which couldn't be imported
+    pub invalid :    Vec<String>,
+}
+
+/// turns a folder name or TAGS entry into a valid tag, e.g.
+/// "Reading List" becomes "reading-list"
+pub fn tag_from_name(name : &str) -> Option<String> {
+    let tag = name
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect::<String>();
+
+    Some(tag).filter(|tag| models::valid_tag(tag))
+}
+
+/// the value of an attribute in the inside of a tag, e.g.
+/// `A HREF="https://ear7h.net" ADD_DATE="0"`
+fn attr(tag : &str, name : &str) -> Option<String> {
+    let upper = tag.to_ascii_uppercase();
+    let needle = format!("{}=\"", name);
+
+    // attributes are preceded by whitespace, this avoids matching the end of
+    // a longer attribute name
+    let start = upper
+        .match_indices(&needle)
+        .map(|(i, _)| i)
+        .find(|&i| i > 0 && upper[..i].ends_with(char::is_whitespace))?
+        + needle.len();
+
+    let end = tag[start..].find('"')? + start;
+
+    Some(unescape(&tag[start..end]))
+}
+
+/// parses all of the bookmarks in the file, folder names are added to the
+/// bookmark's tags. malformed entries are skipped rather than failing the
+/// whole file.
+pub fn parse(html : &str) -> Vec<Bookmark> {
+    let mut bookmarks : Vec<Bookmark> = Vec::new();
+    // the folders containing the current position
+    let mut folders : Vec<Option<String>> = Vec::new();
+    // the name of the last folder header, which the next <DL> belongs to
+    let mut pending_folder = None;
+
+    let mut rest = html;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+
+        let close = match rest.find('>') {
+            Some(close) => close,
+            None => break,
+        };
+        let tag = &rest[..close];
+        rest = &rest[close + 1..];
+
+        let name = tag
+            .split_whitespace()
+            .next()
+            .unwrap_or("")
+            .to_ascii_uppercase();
+
+        match name.as_str() {
+            "H3" => {
+                if let Some(end) = find_ignore_case(rest, "</H3>") {
+                    pending_folder = Some(unescape(rest[..end].trim()));
+                    rest = &rest[end + "</H3>".len()..];
+                }
+            },
+            "DL" => {
+                folders.push(pending_folder.take());
+            },
+            "/DL" => {
+                folders.pop();
+            },
+            "A" => {
+                let url = match attr(tag, "HREF") {
+                    Some(url) => url,
+                    None => continue,
+                };
+
+                let end = find_ignore_case(rest, "</A>").unwrap_or(rest.len());
+                let title = unescape(rest[..end].trim());
+                rest = &rest[(end + "</A>".len()).min(rest.len())..];
+
+                let mut tags = folders
+                    .iter()
+                    .flatten()
+                    .filter_map(|folder| tag_from_name(folder))
+                    .collect::<Vec<_>>();
+
+                if let Some(attr_tags) = attr(tag, "TAGS") {
+                    tags.extend(attr_tags.split(',').filter_map(tag_from_name));
+                }
+
+                tags.sort();
+                tags.dedup();
+
+                bookmarks.push(Bookmark {
+                    url,
+                    title : Some(title).filter(|t| !t.is_empty()),
+                    description : None,
+                    add_date : attr(tag, "ADD_DATE")
+                        .and_then(|d| d.parse().ok()),
+                    tags,
+                });
+            },
+            // the text after a <DD> describes the last bookmark
+            "DD" => {
+                let end = rest.find('<').unwrap_or(rest.len());
+                let description = unescape(rest[..end].trim());
+
+                if let Some(last) = bookmarks.last_mut() {
+                    if !description.is_empty() {
+                        last.description = Some(description);
+                    }
+                }
+
+                rest = &rest[end..];
+            },
+            _ => {},
+        }
+    }
+
+    bookmarks
+}
+
+pub const HEADER : &str = "<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+";
+
+pub const FOOTER : &str = "</DL><p>\n";
+
+/// a single bookmark, to be written between HEADER and FOOTER
+pub fn entry(link : &models::Link) -> String {
+    let mut s = format!(
+        "    <DT><A HREF=\"{}\" ADD_DATE=\"{}\"",
+        escape(&link.url),
+        link.created.unix_timestamp(),
+    );
+
+    if !link.tags.0.is_empty() {
+        s += &format!(" TAGS=\"{}\"", escape(&link.tags.0.join(",")));
+    }
+
+    s += &format!(
+        ">{}</A>\n",
+        escape(link.title.as_deref().unwrap_or(&link.url)),
+    );
+
+    if let Some(description) = &link.description {
+        s += &format!("    <DD>{}\n", escape(description));
+    }
+
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_from_name_normalizes() {
+        assert_eq!(tag_from_name("Reading List"), Some("reading-list".into()));
+        assert_eq!(tag_from_name("  rust  "), Some("rust".into()));
+        assert_eq!(tag_from_name("C++ & Rust"), Some("c--rust".into()));
+        assert_eq!(tag_from_name("???"), None);
+        assert_eq!(tag_from_name(""), None);
+    }
+
+    #[test]
+    fn parse_folders_and_tags() {
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><H3 ADD_DATE="1">Reading List</H3>
+    <DL><p>
+        <DT><A HREF="https://ear7h.net/a?x=1&amp;y=2" ADD_DATE="1600000000" TAGS="rust,Web Dev">A &amp; B</A>
+        <DD>the first one
+        <DT><H3>Nested</H3>
+        <DL><p>
+            <DT><a href="https://ear7h.net/b"></a>
+        </DL><p>
+    </DL><p>
+    <DT><A HREF="https://ear7h.net/c" ADD_DATE="nope">C</A>
+</DL><p>
+"#;
+
+        let bookmarks = parse(html);
+        assert_eq!(bookmarks.len(), 3);
+
+        let a = &bookmarks[0];
+        assert_eq!(a.url, "https://ear7h.net/a?x=1&y=2");
+        assert_eq!(a.title.as_deref(), Some("A & B"));
+        assert_eq!(a.description.as_deref(), Some("the first one"));
+        assert_eq!(a.add_date, Some(1600000000));
+        assert_eq!(a.tags, vec!["reading-list", "rust", "web-dev"]);
+
+        let b = &bookmarks[1];
+        assert_eq!(b.url, "https://ear7h.net/b");
+        assert_eq!(b.title, None);
+        assert_eq!(b.tags, vec!["nested", "reading-list"]);
+
+        let c = &bookmarks[2];
+        assert_eq!(c.add_date, None);
+        assert!(c.tags.is_empty());
+    }
+
+    #[test]
+    fn parse_skips_anchors_without_href() {
+        let bookmarks = parse("<DL><DT><A NAME=\"x\">x</A><DT><A HREF=\"u\">u</A></DL>");
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks[0].url, "u");
+    }
+}
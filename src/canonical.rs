@@ -0,0 +1,185 @@
+//! canonical urls, used to tell when two urls are the same page. the
+//! canonical form is only a key for finding duplicates, links keep the url
+//! they were saved with.
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// query parameters which are removed, matched without case. a trailing
+    /// * matches any parameter starting with the rest.
+    pub strip_params :         Vec<String>,
+    /// http urls are taken to be the same as https ones
+    pub https :                bool,
+    /// /a/ is taken to be the same as /a
+    pub strip_trailing_slash : bool,
+    /// ?b=1&a=2 is taken to be the same as ?a=2&b=1
+    pub sort_query :           bool,
+    /// #section is dropped, turn this off for sites which route on fragments
+    pub strip_fragment :       bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let strip_params = [
+            "utm_*", "fbclid", "gclid", "gclsrc", "dclid", "msclkid", "yclid",
+            "twclid", "igshid", "mc_cid", "mc_eid", "_hsenc", "_hsmi",
+            "mkt_tok", "ref_src",
+        ];
+
+        Self {
+            strip_params :         strip_params
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            https :                true,
+            strip_trailing_slash : true,
+            sort_query :           true,
+            strip_fragment :       true,
+        }
+    }
+}
+
+impl Config {
+    fn strips(&self, key : &str) -> bool {
+        let key = key.to_ascii_lowercase();
+
+        self.strip_params.iter().any(|pattern| {
+            let pattern = pattern.to_ascii_lowercase();
+            match pattern.strip_suffix('*') {
+                Some(prefix) => key.starts_with(prefix),
+                None => key == pattern,
+            }
+        })
+    }
+}
+
+/// the canonical form of the url. url::Url already lowercases the scheme and
+/// host and drops default ports, the rest is up to the config. urls which
+/// aren't http(s) are left as they are.
+pub fn canonicalize(url : &Url, config : &Config) -> String {
+    let mut url = url.clone();
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return url.into()
+    }
+
+    if config.https && url.scheme() == "http" {
+        // https:443 becomes the default port here, and is dropped
+        let _ = url.set_scheme("https");
+    }
+
+    // a trailing dot makes the domain fully qualified, it's the same host
+    let host = url.host_str()
+        .filter(|host| host.len() > 1 && host.ends_with('.'))
+        .map(|host| host.trim_end_matches('.').to_string());
+    if let Some(host) = host {
+        let _ = url.set_host(Some(&host));
+    }
+
+    if config.strip_trailing_slash {
+        let path = url.path().trim_end_matches('/').to_string();
+        url.set_path(if path.is_empty() { "/" } else { path.as_str() });
+    }
+
+    // the pairs are kept as written, decoding and encoding them again could
+    // change how the server reads them
+    let mut pairs = url.query()
+        .unwrap_or("")
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .filter(|pair| !config.strips(pair.split('=').next().unwrap_or("")))
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+    if config.sort_query {
+        // stable, so repeated keys stay in order
+        pairs.sort_by(|a, b| {
+            a.split('=').next().cmp(&b.split('=').next())
+        });
+    }
+
+    if pairs.is_empty() {
+        url.set_query(None);
+    } else {
+        url.set_query(Some(&pairs.join("&")));
+    }
+
+    if config.strip_fragment {
+        url.set_fragment(None);
+    }
+
+    url.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canon(url : &str) -> String {
+        canonicalize(&Url::parse(url).unwrap(), &Config::default())
+    }
+
+    #[test]
+    fn same_page() {
+        let want = "https://ear7h.net/a?b=2&c=1";
+
+        for url in &[
+            "https://ear7h.net/a?b=2&c=1",
+            "http://ear7h.net/a?b=2&c=1",
+            "https://EAR7H.net.:443/a/?c=1&b=2",
+            "https://ear7h.net/a?utm_source=x&b=2&fbclid=y&c=1#top",
+            "https://ear7h.net/a?b=2&UTM_Medium=z&c=1",
+        ] {
+            assert_eq!(canon(url), want, "{}", url);
+        }
+    }
+
+    #[test]
+    fn different_pages() {
+        assert_ne!(canon("https://ear7h.net/a"), canon("https://ear7h.net/b"));
+        assert_ne!(
+            canon("https://ear7h.net/a?b=1"),
+            canon("https://ear7h.net/a?b=2"),
+        );
+        assert_ne!(
+            canon("https://ear7h.net:8443/a"),
+            canon("https://ear7h.net/a"),
+        );
+    }
+
+    #[test]
+    fn query_kept_as_written() {
+        assert_eq!(canon("https://ear7h.net/?utm_x=1"), "https://ear7h.net/");
+        assert_eq!(
+            canon("https://ear7h.net/?q=a%20b&q=c"),
+            "https://ear7h.net/?q=a%20b&q=c",
+        );
+        assert_eq!(
+            canon("https://ear7h.net/?b=1&a=2&b=0"),
+            "https://ear7h.net/?a=2&b=1&b=0",
+        );
+    }
+
+    #[test]
+    fn config_off() {
+        let config = Config {
+            strip_params :         Vec::new(),
+            https :                false,
+            strip_trailing_slash : false,
+            sort_query :           false,
+            strip_fragment :       false,
+        };
+        let url = "http://ear7h.net/a/?utm_source=x&b=1&a=2#top";
+
+        assert_eq!(canonicalize(&Url::parse(url).unwrap(), &config), url);
+    }
+
+    #[test]
+    fn other_schemes() {
+        assert_eq!(canon("mailto:julio@ear7h.net"), "mailto:julio@ear7h.net");
+        assert_eq!(canon("ftp://ear7h.net/a/#b"), "ftp://ear7h.net/a/#b");
+    }
+}
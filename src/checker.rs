@@ -0,0 +1,420 @@
+//! the checker periodically makes sure saved links still work, recording
+//! which ones are dead and which redirect somewhere else
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use hyper::client::HttpConnector;
+use hyper::{Body, Client, Method};
+use hyper_rustls::HttpsConnector;
+use serde::Deserialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::api::Server;
+use crate::netguard::{self, Guard};
+use crate::{archiver, models, Error, Result};
+
+/// links checked per database query
+const BATCH_SIZE : u32 = 64;
+/// how long to wait before looking for due links again
+const IDLE_INTERVAL : Duration = Duration::from_secs(10 * 60);
+const CHECK_TIMEOUT : Duration = Duration::from_secs(30);
+const MAX_REDIRECTS : usize = 10;
+/// failing links are checked up to 2^MAX_BACKOFF times less often
+const MAX_BACKOFF : u32 = 5;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// how often working links are checked, at least daily
+    pub interval_days : u32,
+    /// checks in flight at once
+    pub concurrency :   usize,
+    /// checks in flight at once against a single host
+    pub per_host :      usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            interval_days : 7,
+            concurrency :   8,
+            per_host :      1,
+        }
+    }
+}
+
+/// the outcome of checking a link
+#[derive(Debug, Default)]
+pub struct Check {
+    /// the status of the last response
+    pub status :   Option<u16>,
+    /// where the link ended up after redirects, if it's somewhere else
+    pub location : Option<String>,
+    /// set when no response was received, e.g. dns failures and timeouts
+    pub error :    Option<String>,
+}
+
+impl Check {
+    pub fn is_broken(&self) -> bool {
+        self.error.is_some() || self.status.map(|s| s >= 400).unwrap_or(true)
+    }
+}
+
+pub struct Checker {
+    config :  Config,
+    client :  Client<HttpsConnector<HttpConnector<netguard::Resolver>>>,
+    guard :   Guard,
+    timeout : Duration,
+    all :     Arc<Semaphore>,
+    hosts :   Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl Checker {
+    pub fn new(config : Config, guard : Guard) -> Self {
+        Self {
+            all :     Arc::new(Semaphore::new(config.concurrency.max(1))),
+            hosts :   Mutex::new(HashMap::new()),
+            client :  Client::builder().build(guard.connector()),
+            guard,
+            timeout : CHECK_TIMEOUT,
+            config,
+        }
+    }
+
+    /// when a link should be checked next, failing links back off
+    /// exponentially
+    fn next_check(&self, failures : u32) -> models::Time {
+        let days = self.config.interval_days.max(1);
+        let interval = time::Duration::days(days.into());
+        let backoff = 2i32.pow(failures.min(MAX_BACKOFF));
+
+        (time::OffsetDateTime::now_utc() + interval * backoff).into()
+    }
+
+    async fn host_permit(&self, host : &str) -> OwnedSemaphorePermit {
+        let sem = {
+            let mut hosts = self.hosts.lock().unwrap();
+
+            // semaphores only the map holds aren't being waited on or held,
+            // so the map only grows with the hosts being checked
+            hosts.retain(|_, sem| Arc::strong_count(sem) > 1);
+
+            hosts
+                .entry(host.to_string())
+                .or_insert_with(|| {
+                    Arc::new(Semaphore::new(self.config.per_host.max(1)))
+                })
+                .clone()
+        };
+
+        sem.acquire_owned().await.unwrap()
+    }
+
+    async fn request(
+        &self,
+        method : Method,
+        url : &url::Url,
+    ) -> Result<http::Response<Body>> {
+        let req = http::Request::builder()
+            .method(method)
+            .uri(url.as_str())
+            .header(http::header::USER_AGENT, archiver::USER_AGENT)
+            .body(Body::empty())
+            .map_err(|_| Error::InvalidUrl(url.to_string()))?;
+
+        Ok(self.client.request(req).await?)
+    }
+
+    /// checks the url, limited per host. HEAD is tried first and GET is used
+    /// for servers which don't support it.
+    pub async fn check(&self, url : &str) -> Check {
+        let _all = self.all.clone().acquire_owned().await.unwrap();
+
+        let res = tokio::time::timeout(self.timeout, self.check_inner(url))
+            .await
+            .unwrap_or_else(|_| {
+                Err(Error::FetchFailed(format!("timed out: {}", url)))
+            });
+
+        res.unwrap_or_else(|err| Check {
+            error : Some(format!("{:?}", err)),
+            ..Check::default()
+        })
+    }
+
+    async fn check_inner(&self, orig : &str) -> Result<Check> {
+        let mut url = url::Url::parse(orig)
+            .map_err(|_| Error::InvalidUrl(orig.to_string()))?;
+        let start = url.clone();
+
+        for _ in 0..=MAX_REDIRECTS {
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(Error::FetchFailed(format!(
+                    "unsupported scheme: {}",
+                    url
+                )))
+            }
+
+            self.guard.check_url(&url)?;
+
+            let res = {
+                let host = url.host_str().unwrap_or("");
+                let _host = self.host_permit(host).await;
+
+                let res = self.request(Method::HEAD, &url).await?;
+                match res.status().as_u16() {
+                    405 | 501 => self.request(Method::GET, &url).await?,
+                    _ => res,
+                }
+            };
+
+            let location = res
+                .headers()
+                .get(http::header::LOCATION)
+                .and_then(|loc| loc.to_str().ok());
+
+            if res.status().is_redirection() {
+                if let Some(location) = location {
+                    url = url.join(location)
+                        .map_err(|_| Error::InvalidUrl(location.to_string()))?;
+                    continue
+                }
+            }
+
+            return Ok(Check {
+                status :   Some(res.status().as_u16()),
+                location : Some(url)
+                    .filter(|url| *url != start)
+                    .map(String::from),
+                error :    None,
+            })
+        }
+
+        Err(Error::FetchFailed(format!("too many redirects: {}", url)))
+    }
+}
+
+/// checks links as they become due, forever. meant to be spawned when the
+/// server starts.
+pub async fn run(server : Server) {
+    loop {
+        let links = match server.db.get_links_to_check(BATCH_SIZE).await {
+            Ok(links) => links,
+            Err(err) => {
+                log!(Warn, "checker failed", error = format!("{:?}", err));
+                tokio::time::sleep(IDLE_INTERVAL).await;
+                continue
+            },
+        };
+
+        if links.is_empty() {
+            tokio::time::sleep(IDLE_INTERVAL).await;
+            continue
+        }
+
+        // the semaphores keep this from going too fast
+        let tasks = links
+            .into_iter()
+            .map(|(link_id, url, failures)| {
+                let server = server.clone();
+
+                tokio::spawn(async move {
+                    let check = server.checker.check(&url).await;
+
+                    let failures = if check.is_broken() {
+                        failures + 1
+                    } else {
+                        0
+                    };
+                    let next_check = server.checker.next_check(failures);
+
+                    let res = server.db
+                        .insert_check(link_id, &check, failures, &next_check)
+                        .await;
+                    if let Err(err) = res {
+                        log!(
+                            Warn,
+                            "checker failed",
+                            link_id = link_id,
+                            error = format!("{:?}", err),
+                        );
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for task in tasks {
+            if let Err(err) = task.await {
+                log!(Warn, "checker failed", error = format!("{:?}", err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Request, Response, Server};
+
+    use super::*;
+
+    async fn route(
+        req : Request<Body>,
+    ) -> std::result::Result<Response<Body>, Infallible> {
+        let head = req.method() == Method::HEAD;
+
+        let status = match req.uri().path() {
+            "/ok" => 200,
+            "/no-head" if head => 405,
+            "/no-head" => 200,
+            "/moved" => {
+                return Ok(Response::builder()
+                    .status(301)
+                    .header(http::header::LOCATION, "/ok")
+                    .body(Body::empty())
+                    .unwrap())
+            },
+            "/error" => 500,
+            "/slow" => {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                200
+            },
+            _ => 404,
+        };
+
+        Ok(Response::builder().status(status).body(Body::empty()).unwrap())
+    }
+
+    /// serves the routes above on a free port
+    fn serve() -> SocketAddr {
+        let make_service = make_service_fn(|_| async {
+            Ok::<_, Infallible>(service_fn(route))
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        addr
+    }
+
+    fn checker(config : Config) -> Checker {
+        let guard = Guard::new(netguard::Config {
+            allow : vec![[127, 0, 0, 1].into()],
+        });
+
+        Checker::new(config, guard)
+    }
+
+    #[tokio::test]
+    async fn check_working() {
+        let addr = serve();
+        let check = checker(Config::default())
+            .check(&format!("http://{}/ok", addr))
+            .await;
+
+        assert_eq!(check.status, Some(200));
+        assert_eq!(check.location, None);
+        assert!(!check.is_broken());
+    }
+
+    #[tokio::test]
+    async fn check_falls_back_to_get() {
+        let addr = serve();
+        let check = checker(Config::default())
+            .check(&format!("http://{}/no-head", addr))
+            .await;
+
+        assert_eq!(check.status, Some(200));
+        assert!(!check.is_broken());
+    }
+
+    #[tokio::test]
+    async fn check_records_redirects() {
+        let addr = serve();
+        let check = checker(Config::default())
+            .check(&format!("http://{}/moved", addr))
+            .await;
+
+        assert_eq!(check.status, Some(200));
+        assert_eq!(check.location, Some(format!("http://{}/ok", addr)));
+        assert!(!check.is_broken());
+    }
+
+    #[tokio::test]
+    async fn check_broken_statuses() {
+        let addr = serve();
+        let checker = checker(Config::default());
+
+        for (path, status) in &[("/gone", 404), ("/error", 500)] {
+            let url = format!("http://{}{}", addr, path);
+            let check = checker.check(&url).await;
+
+            assert_eq!(check.status, Some(*status));
+            assert_eq!(check.error, None);
+            assert!(check.is_broken());
+        }
+
+        // only hosts being checked are kept around
+        assert!(checker.hosts.lock().unwrap().len() <= 1);
+    }
+
+    #[tokio::test]
+    async fn check_timeout() {
+        let addr = serve();
+        let mut checker = checker(Config::default());
+        checker.timeout = Duration::from_millis(100);
+
+        let check = checker.check(&format!("http://{}/slow", addr)).await;
+
+        assert_eq!(check.status, None);
+        assert!(check.error.unwrap().contains("timed out"));
+    }
+
+    #[tokio::test]
+    async fn check_refuses_private_addresses() {
+        let addr = serve();
+        let checker = Checker::new(Config::default(), Guard::default());
+
+        let check = checker.check(&format!("http://{}/ok", addr)).await;
+
+        assert_eq!(check.status, None);
+        assert!(check.error.unwrap().contains("address not allowed"));
+    }
+
+    /// the whole days until the next check
+    fn days_until(checker : &Checker, failures : u32) -> i64 {
+        let now = time::OffsetDateTime::now_utc();
+        let next : time::OffsetDateTime = *checker.next_check(failures);
+
+        (next - now).whole_days()
+    }
+
+    #[test]
+    fn next_check_backs_off() {
+        let checker = checker(Config::default());
+
+        assert_eq!(days_until(&checker, 0), 7);
+        assert_eq!(days_until(&checker, 1), 14);
+        assert_eq!(days_until(&checker, 3), 56);
+        assert_eq!(days_until(&checker, MAX_BACKOFF), 7 * 32);
+        assert_eq!(days_until(&checker, 100), 7 * 32);
+    }
+
+    #[test]
+    fn next_check_at_least_daily() {
+        let checker = checker(Config {
+            interval_days : 0,
+            ..Config::default()
+        });
+
+        assert_eq!(days_until(&checker, 0), 1);
+        assert_eq!(days_until(&checker, 1), 2);
+    }
+}
@@ -0,0 +1,60 @@
+//! csrf tokens for the html forms. a token is the hmac of the session the
+//! form was rendered for: the auth cookie once logged in, and a random cookie
+//! set by the login page before that. other sites can't read either cookie,
+//! so they can't make a token which matches.
+
+use rand::RngCore;
+
+use crate::hashing;
+
+/// the cookie holding the session before logging in
+pub const COOKIE_NAME : &str = "ear7h-csrf";
+
+/// the form field with the token
+pub const FIELD : &str = "csrf";
+
+/// the header with the token, for scripts which don't post forms
+pub const HEADER : &str = "x-csrf-token";
+
+pub struct Csrf {
+    key : Vec<u8>,
+}
+
+impl Csrf {
+    /// a random key is used when there's no secret, forms rendered before
+    /// the server restarts can't be posted after
+    pub fn new(secret : Option<&str>) -> Self {
+        let key = match secret {
+            Some(secret) => secret.as_bytes().to_vec(),
+            None => {
+                let mut key = vec![0u8; 32];
+                rand::thread_rng().fill_bytes(&mut key);
+                key
+            },
+        };
+
+        Self { key }
+    }
+
+    pub fn token(&self, session : &str) -> String {
+        hashing::hex(&hashing::hmac_sha256(&self.key, session.as_bytes()))
+    }
+
+    pub fn verify(&self, session : &str, token : &str) -> bool {
+        let expected = self.token(session);
+
+        // compares every byte so the time taken doesn't say how much matched
+        expected.len() == token.len() && expected
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+    }
+}
+
+/// a new random session for the login page
+pub fn session() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    hashing::hex(&bytes)
+}
@@ -1,59 +1,1665 @@
-use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
-use rusqlite::{ffi, Connection};
+use std::collections::HashMap;
+
+use rusqlite::types::{
+    FromSql,
+    FromSqlError,
+    FromSqlResult,
+    ToSql,
+    ToSqlOutput,
+    ValueRef,
+};
+use rand::RngCore;
+use rusqlite::{ffi, Connection, OptionalExtension, TransactionBehavior};
 use tokio::sync::Mutex;
 
-use crate::time_utils::TIME_FORMAT;
-use crate::{models, Error, Result};
+use crate::hashing;
+use crate::time_utils::TIME_FORMAT;
+use crate::{archiver, canonical, checker, metadata, models, Error, Result};
+
+fn error_code_match(
+    err : &rusqlite::Error,
+    code : ffi::ErrorCode,
+    ext : i64,
+) -> bool {
+    matches!(
+            err,
+            rusqlite::Error::SqliteFailure(e, _)
+                if e.code == code
+                && i64::from(e.extended_code) == ext)
+}
+
+macro_rules! db_method {
+    ($(#[$attr:meta])* $name:ident (
+        &$self:ident,
+        $conn:ident,
+        $($pname:ident : $ptype:ty),*
+    ) -> $ret:ty $body:block ) => {
+        $(#[$attr])*
+        pub async fn $name (&$self, $( $pname : $ptype, )* ) -> $ret {
+            let start = std::time::Instant::now();
+            let $conn = $self.conn.lock().await;
+            let res = tokio::task::block_in_place(|| $body);
+            drop($conn);
+
+            // includes waiting for the connection
+            let ms = start.elapsed().as_secs_f64() * 1000.0;
+            log!(
+                Debug,
+                "db",
+                method = stringify!($name),
+                ok = res.is_ok(),
+                ms = format!("{:.1}", ms),
+            );
+
+            res
+        }
+    }
+}
+
+macro_rules! migrations {
+    ($($name:literal,)*) => {
+        &[
+            $(
+                (
+                    $name,
+                    include_str!(concat!("../sql/migrations/", $name)),
+                ),
+            )*
+        ]
+    }
+}
+
+/// The schema migrations known to this binary, in the order they are
+/// applied. New files in sql/migrations must be added here.
+const MIGRATIONS : &[(&str, &str)] = migrations! {
+    "2021-08-29-init.sql",
+    "2021-09-18-external-password.sql",
+    "2026-10-17-link-ids.sql",
+    "2026-10-17-tags.sql",
+    "2026-10-17-link-details.sql",
+    "2026-10-17-api-tokens.sql",
+    "2026-10-17-search.sql",
+    "2026-10-17-link-sorting.sql",
+    "2026-10-17-snapshots.sql",
+    "2026-10-17-inlined-snapshots.sql",
+    "2026-10-17-rearchive.sql",
+    "2026-10-17-link-checks.sql",
+    "2026-10-17-link-metadata.sql",
+    "2026-10-17-reader.sql",
+    "2026-10-17-canonical-urls.sql",
+    "2026-10-17-collections.sql",
+    "2026-10-17-feeds.sql",
+    "2026-10-17-workspaces.sql",
+    "2026-10-17-admin.sql",
+};
+
+/// Migrations from before they were embedded, which were run with sqlite3.
+/// They open their own transaction and record themselves in the migrations
+/// table, so they are run as is.
+const SELF_RECORDING : &[&str] = &[
+    "2021-08-29-init.sql",
+    "2021-09-18-external-password.sql",
+];
+
+/// Applies the embedded migrations missing from the migrations table, each
+/// in its own transaction. Fails if the database has migrations this binary
+/// does not know about.
+///
+/// Foreign keys are off while migrating so tables can be rebuilt, they are
+/// checked before each migration is committed instead.
+fn migrate(conn : &mut Connection) -> Result<()> {
+    let initialized = conn.query_row(
+        "SELECT count(*) FROM sqlite_master
+        WHERE type = 'table' AND name = 'migrations'",
+        rusqlite::params![],
+        |row| row.get::<_, i64>(0),
+    )? > 0;
+
+    let mut applied = Vec::new();
+    if initialized {
+        let mut stmt = conn.prepare("SELECT name FROM migrations")?;
+        let mut rows = stmt.query(rusqlite::params![])?;
+
+        while let Some(row) = rows.next()? {
+            applied.push(row.get::<_, String>(0)?);
+        }
+    }
+
+    for name in applied.iter() {
+        if !MIGRATIONS.iter().any(|(known, _)| known == name) {
+            return Err(Error::UnknownMigration(name.clone()))
+        }
+    }
+
+    for (name, sql) in MIGRATIONS.iter() {
+        if applied.iter().any(|done| done == name) {
+            continue
+        }
+
+        if SELF_RECORDING.contains(name) {
+            conn.execute_batch(sql)?;
+            continue
+        }
+
+        // can't be changed inside a transaction
+        conn.pragma_update(None, "foreign_keys", &"OFF")?;
+
+        let tx =
+            conn.transaction_with_behavior(TransactionBehavior::Exclusive)?;
+        tx.execute_batch(sql)?;
+
+        let broken = tx
+            .prepare("PRAGMA foreign_key_check")?
+            .exists(rusqlite::params![])?;
+        if broken {
+            return Err(rusqlite::Error::SqliteFailure(
+                ffi::Error::new(ffi::SQLITE_CONSTRAINT_FOREIGNKEY),
+                Some(format!("foreign key check failed after {}", name)),
+            ).into())
+        }
+
+        tx.execute(
+            "INSERT INTO migrations (name) VALUES (?)",
+            rusqlite::params![name],
+        )?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+/// Recomputes the canonical url of every link when the canonical config
+/// differs from the one they were computed with. When two links of a user
+/// have the same canonical url the older one keeps it and the newer one is
+/// left without, each of these is logged so they can be merged by hand.
+fn canonicalize_links(
+    conn : &mut Connection,
+    config : &canonical::Config,
+) -> Result<()> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Exclusive)?;
+
+    let fingerprint = serde_json::to_string(config)?;
+    let computed_with : Option<String> = tx
+        .query_row(
+            "SELECT config FROM canonical_config",
+            rusqlite::params![],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    if computed_with.as_ref() == Some(&fingerprint) {
+        return Ok(())
+    }
+
+    let mut links = Vec::new();
+    {
+        let mut stmt = tx.prepare("
+            SELECT id, user_id, url, canonical FROM links ORDER BY id
+        ")?;
+        let mut rows = stmt.query(rusqlite::params![])?;
+
+        let mut seen = HashMap::new();
+        while let Some(row) = rows.next()? {
+            let id : u32 = row.get(0)?;
+            let user_id : u32 = row.get(1)?;
+            let url : String = row.get(2)?;
+            let old : Option<String> = row.get(3)?;
+
+            let mut new = url::Url::parse(&url)
+                .ok()
+                .map(|url| canonical::canonicalize(&url, config));
+
+            if let Some(canonical) = new.clone() {
+                let first = *seen.entry((user_id, canonical)).or_insert(id);
+                if first != id {
+                    log!(
+                        Warn,
+                        "duplicate link",
+                        user_id = user_id,
+                        link_id = id,
+                        duplicate_of = first,
+                    );
+                    new = None;
+                }
+            }
+
+            links.push((id, old, new));
+        }
+    }
+
+    if links.iter().any(|(_, old, new)| old != new) {
+        // cleared first so the unique index only sees the new values
+        tx.execute("UPDATE links SET canonical = NULL", rusqlite::params![])?;
+
+        let mut stmt =
+            tx.prepare("UPDATE links SET canonical = ? WHERE id = ?")?;
+        for (id, _, new) in links.iter() {
+            if new.is_some() {
+                stmt.execute(rusqlite::params![new, id])?;
+            }
+        }
+    }
+
+    tx.execute(
+        "INSERT OR REPLACE INTO canonical_config (id, config) VALUES (0, ?)",
+        rusqlite::params![fingerprint],
+    )?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+fn set_feed_token(conn : &Connection, user_id : u32) -> Result<String> {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token = hashing::hex(&bytes);
+
+    let n = conn
+        .prepare_cached("UPDATE users SET feed_token = ? WHERE id = ?")?
+        .execute(rusqlite::params![token, user_id])?;
+    if n == 0 {
+        return Err(Error::UserIdNotFound(user_id))
+    }
+
+    Ok(token)
+}
+
+/// fails if the change left the workspace without an owner, so it can be
+/// rolled back
+fn check_owner(conn : &Connection, workspace_id : u32) -> Result<()> {
+    let owned = conn
+        .prepare_cached("
+            SELECT 1 FROM workspace_members
+            WHERE workspace_id = ? AND role = 'owner'
+        ")?
+        .exists(rusqlite::params![workspace_id])?;
+
+    if !owned {
+        return Err(Error::LastOwner)
+    }
+
+    Ok(())
+}
+
+/// the columns of links along with their space separated tags and number of
+/// snapshots
+macro_rules! link_columns {
+    () => {
+        "
+        links.*, (
+            SELECT group_concat(tags.name, ' ')
+            FROM link_tags JOIN tags ON tags.id = link_tags.tag_id
+            WHERE link_tags.link_id = links.id
+        ) AS tags, (
+            SELECT count(*) FROM snapshots
+            WHERE snapshots.link_id = links.id
+            AND snapshots.digest IS NOT NULL
+        ) AS snapshots, (
+            SELECT max(snapshots.fetched) FROM snapshots
+            WHERE snapshots.link_id = links.id
+            AND snapshots.digest IS NOT NULL
+        ) AS last_archived, (
+            SELECT snapshots.words FROM snapshots
+            WHERE snapshots.link_id = links.id
+            AND snapshots.words IS NOT NULL
+            ORDER BY snapshots.fetched DESC, snapshots.id DESC
+            LIMIT 1
+        ) AS words,
+        link_checks.health AS health,
+        link_checks.location AS moved_to,
+        link_checks.checked AS checked
+        "
+    }
+}
+
+/// the tables link_columns!() selects from
+macro_rules! links_join {
+    () => {
+        "links LEFT JOIN link_checks ON link_checks.link_id = links.id"
+    }
+}
+
+/// selects links along with their tags, meant to be used with concat! to add
+/// the WHERE clause
+macro_rules! select_links {
+    () => {
+        concat!("SELECT ", link_columns!(), " FROM ", links_join!())
+    }
+}
+
+/// selects a page of a user's links. the sort key and url of the last link
+/// on the previous page are passed in to continue where it left off.
+macro_rules! select_links_page {
+    ($key:literal, $cmp:literal, $order:literal) => {
+        concat!(
+            "SELECT ", link_columns!(), ", ", $key, " AS sort_key
+            FROM ", links_join!(), "
+            WHERE links.user_id = ? AND links.deleted IS NULL
+            AND (
+                ? IS NULL OR links.id IN (
+                    SELECT link_tags.link_id
+                    FROM link_tags JOIN tags ON tags.id = link_tags.tag_id
+                    WHERE tags.name = ?
+                )
+            )
+            AND (
+                ? IS NULL OR links.id IN (
+                    SELECT collection_links.link_id FROM collection_links
+                    WHERE collection_links.collection_id = ?
+                )
+            )
+            AND (? IS NULL OR link_checks.health = ?)
+            AND (? IS NULL OR (", $key, ", links.url) ", $cmp, " (?, ?))
+            ORDER BY ", $key, " ", $order, ", links.url ", $order, "
+            LIMIT ?"
+        )
+    }
+}
+
+/// selects collections along with how many links they have, meant to be used
+/// with concat! to add the WHERE and GROUP BY clauses
+macro_rules! select_collections {
+    () => {
+        "
+        SELECT collections.*, count(links.id) AS links
+        FROM collections
+        LEFT JOIN collection_links
+            ON collection_links.collection_id = collections.id
+        LEFT JOIN links
+            ON links.id = collection_links.link_id
+            AND links.deleted IS NULL
+        "
+    }
+}
+
+/// turns user input into an fts5 query matching all of the words, so fts5
+/// syntax errors can't happen
+fn fts_query(query : &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub struct Db {
+    conn : Mutex<Connection>,
+}
+
+impl Db {
+    pub fn new<P : AsRef<std::path::Path>>(
+        p : P,
+        canonical : &canonical::Config,
+    ) -> Result<Self> {
+        let mut conn = Connection::open(p)?;
+
+        migrate(&mut conn)?;
+        conn.pragma_update(None, "foreign_keys", &"ON")?;
+
+        canonicalize_links(&mut conn, canonical)?;
+
+        Ok(Self {
+            conn : Mutex::new(conn),
+        })
+    }
+
+    db_method! {insert_user(
+        &self,
+        conn,
+        name : &str,
+        password : &str
+    ) -> Result<()> {
+        conn
+            .prepare_cached("INSERT INTO users (name, password) VALUES (?, ?)")?
+            .execute(rusqlite::params![name, password])
+            .map_err(|err| {
+                if error_code_match(
+                    &err,
+                    ffi::ErrorCode::ConstraintViolation,
+                    2067
+                ) {
+                    Error::DuplicateName(name.to_string())
+                } else {
+                    err.into()
+                }
+            })?;
+        Ok(())
+    }}
+
+    // records a login, creating the user on their first one if they may
+    // register. disabled users can't log in.
+    db_method! {upsert_user(
+        &self,
+        conn,
+        name : &str,
+        may_register : bool
+    ) -> Result<models::User> {
+        let tx = conn.unchecked_transaction()?;
+
+        let found = tx
+            .prepare_cached("
+                SELECT * FROM users
+                WHERE name = ? AND NOT workspace
+            ")?
+            .query(rusqlite::params![name])?
+            .next()?
+            .map(row_parse::<models::User>)
+            .transpose()?;
+
+        let stmt = match found {
+            Some(user) if user.deleted.is_some() => {
+                return Err(Error::UserDisabled(user.name))
+            },
+            Some(_) => "
+                UPDATE users SET last_login = datetime('now', 'utc')
+                WHERE name = ? AND NOT workspace
+                RETURNING *
+            ",
+            None if !may_register => {
+                return Err(Error::RegistrationClosed(name.to_string()))
+            },
+            None => "
+                INSERT INTO users (name, last_login)
+                VALUES (?, datetime('now', 'utc'))
+                RETURNING *
+            ",
+        };
+
+        let user = tx
+            .prepare_cached(stmt)?
+            .query(rusqlite::params![name])?
+            .next()?
+            .map(row_parse::<models::User>)
+            .transpose()?
+            .ok_or(Error::FailedLogin)?;
+
+        tx.commit()?;
+
+        Ok(user)
+    }}
+
+    db_method! {get_user(&self, conn, user_id : u32) -> Result<models::User> {
+        let mut stmt = conn
+            .prepare_cached("SELECT * FROM users WHERE users.id = ?")?;
+
+        let mut rows = stmt.query(rusqlite::params![user_id])?;
+
+        let row = rows.next()?
+            .ok_or(Error::UserIdNotFound(user_id))?;
+
+        row_parse(row)
+    }}
+
+    // only people are found, workspaces can't log in
+    db_method! {get_user_by_name(
+        &self,
+        conn,
+        username : &str
+    ) -> Result<models::User> {
+        let mut stmt = conn
+            .prepare_cached("
+                SELECT * FROM users
+                WHERE users.name = ?
+                AND NOT users.workspace
+            ")?;
+
+        let mut rows = stmt.query(rusqlite::params![username])?;
+
+        let row = rows.next()?
+            .ok_or(Error::UserNameNotFound(username.to_string()))?;
+
+        row_parse(row)
+    }}
+
+    // every account including disabled ones and workspaces, for admins
+    db_method! {get_user_summaries(
+        &self,
+        conn,
+    ) -> Result<Vec<models::UserSummary>> {
+        let mut stmt = conn
+            .prepare_cached("
+                SELECT users.id, users.name, users.created, users.deleted,
+                    users.last_login,
+                    users.workspace,
+                    (
+                        SELECT count(*) FROM links
+                        WHERE links.user_id = users.id
+                        AND links.deleted IS NULL
+                    ) AS links
+                FROM users
+                ORDER BY users.name
+            ")?;
+
+        let mut rows = stmt.query(rusqlite::params![])?;
+
+        let mut users = Vec::new();
+        while let Some(row) = rows.next()? {
+            users.push(row_parse::<models::UserSummary>(row)?);
+        }
+
+        Ok(users)
+    }}
+
+    // disabled users can't log in and their api tokens stop working, their
+    // links are kept so they can be enabled again
+    db_method! {set_user_disabled(
+        &self,
+        conn,
+        user_id : u32,
+        disabled : bool
+    ) -> Result<()> {
+        let n = conn
+            .prepare_cached("
+                UPDATE users SET deleted = CASE
+                    WHEN ? THEN coalesce(deleted, datetime('now', 'utc'))
+                    ELSE NULL
+                END
+                WHERE id = ?
+            ")?
+            .execute(rusqlite::params![disabled, user_id])?;
+
+        if n == 0 {
+            return Err(Error::UserIdNotFound(user_id))
+        }
+
+        Ok(())
+    }}
+
+    // creates the workspace with the user as its owner
+    db_method! {insert_workspace(
+        &self,
+        conn,
+        user_id : u32,
+        name : &str
+    ) -> Result<u32> {
+        let tx = conn.unchecked_transaction()?;
+
+        tx
+            .prepare_cached("
+                INSERT INTO users (name, workspace) VALUES (?, 1)
+            ")?
+            .execute(rusqlite::params![name])
+            .map_err(|err| {
+                if error_code_match(
+                    &err,
+                    ffi::ErrorCode::ConstraintViolation,
+                    2067
+                ) {
+                    Error::DuplicateName(name.to_string())
+                } else {
+                    err.into()
+                }
+            })?;
+
+        let workspace_id = tx.last_insert_rowid() as u32;
+
+        tx
+            .prepare_cached("
+                INSERT INTO workspaces (id, created_by) VALUES (?, ?)
+            ")?
+            .execute(rusqlite::params![workspace_id, user_id])?;
+
+        tx
+            .prepare_cached("
+                INSERT INTO workspace_members (workspace_id, user_id, role)
+                VALUES (?, ?, 'owner')
+            ")?
+            .execute(rusqlite::params![workspace_id, user_id])?;
+
+        tx.commit()?;
+
+        Ok(workspace_id)
+    }}
+
+    // the workspaces the user is a member of
+    db_method! {get_workspaces(
+        &self,
+        conn,
+        user_id : u32
+    ) -> Result<Vec<models::Workspace>> {
+        let mut stmt = conn
+            .prepare_cached("
+                SELECT users.id, users.name, users.created,
+                    workspace_members.role
+                FROM workspace_members
+                JOIN users ON users.id = workspace_members.workspace_id
+                WHERE workspace_members.user_id = ? AND users.deleted IS NULL
+                ORDER BY users.name
+            ")?;
+
+        let mut rows = stmt.query(rusqlite::params![user_id])?;
+
+        let mut workspaces = Vec::new();
+        while let Some(row) = rows.next()? {
+            workspaces.push(row_parse::<models::Workspace>(row)?);
+        }
+
+        Ok(workspaces)
+    }}
+
+    // only found if the user is a member
+    db_method! {get_workspace(
+        &self,
+        conn,
+        workspace_id : u32,
+        user_id : u32
+    ) -> Result<models::Workspace> {
+        let mut stmt = conn
+            .prepare_cached("
+                SELECT users.id, users.name, users.created,
+                    workspace_members.role
+                FROM workspace_members
+                JOIN users ON users.id = workspace_members.workspace_id
+                WHERE workspace_members.workspace_id = ?
+                AND workspace_members.user_id = ?
+                AND users.deleted IS NULL
+            ")?;
+
+        let mut rows = stmt.query(rusqlite::params![workspace_id, user_id])?;
+
+        let row = rows.next()?
+            .ok_or(Error::WorkspaceIdNotFound(workspace_id))?;
+
+        row_parse(row)
+    }}
+
+    // the user's role in the account, none if it isn't a workspace they're a
+    // member of
+    db_method! {get_role(
+        &self,
+        conn,
+        account_id : u32,
+        user_id : u32
+    ) -> Result<Option<models::Role>> {
+        let mut stmt = conn
+            .prepare_cached("
+                SELECT workspace_members.role
+                FROM workspace_members
+                JOIN users ON users.id = workspace_members.workspace_id
+                WHERE workspace_members.workspace_id = ?
+                AND workspace_members.user_id = ?
+                AND users.deleted IS NULL
+            ")?;
+
+        let mut rows = stmt.query(rusqlite::params![account_id, user_id])?;
+
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }}
+
+    db_method! {get_members(
+        &self,
+        conn,
+        workspace_id : u32
+    ) -> Result<Vec<models::Member>> {
+        let mut stmt = conn
+            .prepare_cached("
+                SELECT workspace_members.user_id, users.name,
+                    workspace_members.role, workspace_members.added
+                FROM workspace_members
+                JOIN users ON users.id = workspace_members.user_id
+                WHERE workspace_members.workspace_id = ?
+                ORDER BY users.name
+            ")?;
+
+        let mut rows = stmt.query(rusqlite::params![workspace_id])?;
+
+        let mut members = Vec::new();
+        while let Some(row) = rows.next()? {
+            members.push(row_parse::<models::Member>(row)?);
+        }
+
+        Ok(members)
+    }}
+
+    // adds the user to the workspace, or changes their role if they're
+    // already in it. users are found by name and must have logged in before.
+    db_method! {set_member(
+        &self,
+        conn,
+        workspace_id : u32,
+        name : &str,
+        role : models::Role
+    ) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+
+        let user_id : u32 = tx
+            .prepare_cached("
+                SELECT id FROM users
+                WHERE name = ? AND deleted IS NULL
+                AND NOT workspace
+            ")?
+            .query(rusqlite::params![name])?
+            .next()?
+            .ok_or_else(|| Error::UserNameNotFound(name.to_string()))?
+            .get(0)?;
+
+        tx
+            .prepare_cached("
+                INSERT INTO workspace_members (workspace_id, user_id, role)
+                VALUES (?, ?, ?)
+                ON CONFLICT (workspace_id, user_id)
+                DO UPDATE SET role = excluded.role
+            ")?
+            .execute(rusqlite::params![workspace_id, user_id, role])?;
+
+        check_owner(&tx, workspace_id)?;
+        tx.commit()?;
+
+        Ok(())
+    }}
+
+    db_method! {remove_member(
+        &self,
+        conn,
+        workspace_id : u32,
+        user_id : u32
+    ) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+
+        tx
+            .prepare_cached("
+                DELETE FROM workspace_members
+                WHERE workspace_id = ? AND user_id = ?
+            ")?
+            .execute(rusqlite::params![workspace_id, user_id])?;
+
+        check_owner(&tx, workspace_id)?;
+        tx.commit()?;
+
+        Ok(())
+    }}
+
+    db_method! {insert_api_token(
+        &self,
+        conn,
+        user_id : u32,
+        name : &str,
+        hash : &str
+    ) -> Result<u32> {
+        conn
+            .prepare_cached("
+                INSERT INTO api_tokens (user_id, name, hash) VALUES (?, ?, ?)
+            ")?
+            .execute(rusqlite::params![user_id, name, hash])?;
+
+        Ok(conn.last_insert_rowid() as u32)
+    }}
+
+    db_method! {get_api_tokens(
+        &self,
+        conn,
+        user_id : u32
+    ) -> Result<Vec<models::ApiToken>> {
+        let mut stmt = conn
+            .prepare_cached("
+                SELECT * FROM api_tokens
+                WHERE api_tokens.user_id = ?
+                ORDER BY api_tokens.created DESC
+            ")?;
+
+        let mut rows = stmt
+            .query(rusqlite::params![user_id])?;
+
+        let mut tokens = Vec::new();
+        while let Some(row) = rows.next()? {
+            tokens.push(row_parse::<models::ApiToken>(row)?);
+        }
+
+        Ok(tokens)
+    }}
+
+    db_method! {revoke_api_token(
+        &self,
+        conn,
+        user_id : u32,
+        token_id : u32
+    ) -> Result<()> {
+        let n = conn
+            .prepare_cached("
+                UPDATE api_tokens SET revoked = datetime('now', 'utc')
+                WHERE id = ? AND user_id = ? AND revoked IS NULL
+            ")?
+            .execute(rusqlite::params![token_id, user_id])?;
+
+        if n == 0 {
+            return Err(Error::ApiTokenIdNotFound(token_id))
+        }
+
+        Ok(())
+    }}
+
+    // looks up the owner of an unrevoked token and marks the token as used
+    db_method! {use_api_token(
+        &self,
+        conn,
+        hash : &str
+    ) -> Result<u32> {
+        let mut stmt = conn
+            .prepare_cached("
+                UPDATE api_tokens SET last_used = datetime('now', 'utc')
+                WHERE hash = ? AND revoked IS NULL
+                RETURNING user_id
+            ")?;
+
+        let mut rows = stmt.query(rusqlite::params![hash])?;
+
+        let row = rows.next()?
+            .ok_or(Error::FailedLogin)?;
+
+        Ok(row.get(0)?)
+    }}
+
+    // the token is made the first time it's asked for
+    db_method! {get_feed_token(&self, conn, user_id : u32) -> Result<String> {
+        let token : Option<String> = conn
+            .prepare_cached("SELECT feed_token FROM users WHERE id = ?")?
+            .query_row(rusqlite::params![user_id], |row| row.get(0))
+            .optional()?
+            .ok_or(Error::UserIdNotFound(user_id))?;
+
+        match token {
+            Some(token) => Ok(token),
+            None => set_feed_token(&conn, user_id),
+        }
+    }}
+
+    // the old feed urls stop working
+    db_method! {reset_feed_token(&self, conn, user_id : u32) -> Result<String> {
+        set_feed_token(&conn, user_id)
+    }}
+
+    db_method! {use_feed_token(&self, conn, token : &str) -> Result<u32> {
+        conn
+            .prepare_cached("SELECT id FROM users WHERE feed_token = ?")?
+            .query_row(rusqlite::params![token], |row| row.get(0))
+            .optional()?
+            .ok_or(Error::FailedLogin)
+    }}
+
+    db_method! {get_links_changed(
+        &self,
+        conn,
+        user_id : u32
+    ) -> Result<Option<models::Time>> {
+        conn
+            .prepare_cached("SELECT links_changed FROM users WHERE id = ?")?
+            .query_row(rusqlite::params![user_id], |row| row.get(0))
+            .optional()?
+            .ok_or(Error::UserIdNotFound(user_id))
+    }}
+
+    // created defaults to now, it's set when importing links saved elsewhere.
+    // a link with the same url or canonical url is reported by DuplicateUrl,
+    // after taking it out of the trash if it was there.
+    db_method! {insert_link(
+        &self,
+        conn,
+        user_id : u32,
+        link : &str,
+        canonical : &str,
+        created : Option<&models::Time>
+    ) -> Result<u32> {
+        let res = conn
+            .prepare_cached("
+                INSERT INTO links (user_id, url, canonical, created)
+                VALUES (?, ?, ?, coalesce(?, datetime('now', 'utc')))
+            ")?
+            .execute(rusqlite::params![user_id, link, canonical, created]);
+
+        match res {
+            Ok(_) => Ok(conn.last_insert_rowid() as u32),
+            Err(err) if error_code_match(
+                &err,
+                ffi::ErrorCode::ConstraintViolation,
+                2067
+            ) => {
+                let (id, url) : (u32, String) = conn
+                    .prepare_cached("
+                        SELECT id, url FROM links
+                        WHERE user_id = ? AND (url = ? OR canonical = ?)
+                        ORDER BY deleted IS NOT NULL, id
+                        LIMIT 1
+                    ")?
+                    .query_row(
+                        rusqlite::params![user_id, link, canonical],
+                        |row| Ok((row.get(0)?, row.get(1)?)),
+                    )?;
+
+                // saving it again is taken as wanting it back
+                conn
+                    .prepare_cached("
+                        UPDATE links SET deleted = NULL
+                        WHERE id = ? AND deleted IS NOT NULL
+                    ")?
+                    .execute(rusqlite::params![id])?;
+
+                Err(Error::DuplicateUrl(url, id))
+            },
+            Err(err) => Err(err.into()),
+        }
+    }}
+
+    // cursor is the next field of the previous page
+    db_method! {#[allow(clippy::too_many_arguments)] get_links_page(
+        &self,
+        conn,
+        user_id : u32,
+        tag : Option<&str>,
+        collection_id : Option<u32>,
+        health : Option<models::Health>,
+        sort : models::Sort,
+        cursor : Option<&str>,
+        limit : u32
+    ) -> Result<models::LinksPage> {
+        // urls never contain spaces
+        let (key, url) = match cursor {
+            Some(cursor) => {
+                let (key, url) = cursor.rsplit_once(' ')
+                    .ok_or(Error::BadRequest)?;
+                (Some(key), Some(url))
+            },
+            None => (None, None),
+        };
+
+        let mut stmt = conn.prepare_cached(match sort {
+            models::Sort::Newest => {
+                select_links_page!("links.created", "<", "DESC")
+            },
+            models::Sort::Oldest => {
+                select_links_page!("links.created", ">", "ASC")
+            },
+            models::Sort::Domain => {
+                select_links_page!("links.domain", ">", "ASC")
+            },
+        })?;
+
+        // one extra row tells whether there's a next page
+        let mut rows = stmt.query(rusqlite::params![
+            user_id,
+            tag,
+            tag,
+            collection_id,
+            collection_id,
+            health,
+            health,
+            key,
+            key,
+            url,
+            limit + 1,
+        ])?;
+
+        let mut links = Vec::new();
+        let mut last_key = String::new();
+        let mut has_next = false;
+        while let Some(row) = rows.next()? {
+            if links.len() == limit as usize {
+                has_next = true;
+                break
+            }
+
+            last_key = row.get("sort_key")?;
+            links.push(row_parse::<models::Link>(row)?);
+        }
+
+        let next = links
+            .last()
+            .filter(|_| has_next)
+            .map(|link| format!("{} {}", last_key, link.url));
+
+        Ok(models::LinksPage {
+            links,
+            sort,
+            health,
+            next,
+        })
+    }}
+
+    db_method! {search_links(
+        &self,
+        conn,
+        user_id : u32,
+        query : &str
+    ) -> Result<Vec<models::SearchResult>> {
+        let query = fts_query(query);
+        if query.is_empty() {
+            return Ok(Vec::new())
+        }
+
+        // the snippet highlights are marked with control characters so they
+        // can be told apart from the page text when rendering
+        let mut stmt = conn
+            .prepare_cached(concat!("SELECT ", link_columns!(), ",
+                    snippet(links_fts, -1, char(2), char(3), '…', 16)
+                        AS snippet
+                FROM links_fts JOIN links ON links.id = links_fts.rowid
+                LEFT JOIN link_checks ON link_checks.link_id = links.id
+                WHERE links_fts MATCH ?
+                AND links.user_id = ? AND links.deleted IS NULL
+                ORDER BY rank
+            "))?;
+
+        let mut rows = stmt
+            .query(rusqlite::params![query, user_id])?;
+
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            results.push(models::SearchResult {
+                link :    row_parse(row)?,
+                snippet : row.get("snippet")?,
+            });
+        }
+
+        Ok(results)
+    }}
+
+    db_method! {get_link(
+        &self,
+        conn,
+        user_id : u32,
+        link_id : u32
+    ) -> Result<models::Link> {
+        let mut stmt = conn
+            .prepare_cached(concat!(select_links!(), "
+                WHERE links.id = ? AND links.user_id = ?
+            "))?;
+
+        let mut rows = stmt.query(rusqlite::params![link_id, user_id])?;
+
+        let row = rows.next()?
+            .ok_or(Error::LinkIdNotFound(link_id))?;
+
+        row_parse(row)
+    }}
+
+    db_method! {update_link(
+        &self,
+        conn,
+        user_id : u32,
+        link_id : u32,
+        title : Option<&str>,
+        description : Option<&str>,
+        notes : Option<&str>
+    ) -> Result<()> {
+        let n = conn
+            .prepare_cached("
+                UPDATE links SET title = ?, description = ?, notes = ?
+                WHERE id = ? AND user_id = ?
+            ")?
+            .execute(rusqlite::params![
+                title,
+                description,
+                notes,
+                link_id,
+                user_id
+            ])?;
+
+        if n == 0 {
+            return Err(Error::LinkIdNotFound(link_id))
+        }
+
+        Ok(())
+    }}
+
+    // links including the ones in the trash
+    db_method! {get_all_links(
+        &self,
+        conn,
+        user_id : u32
+    ) -> Result<Vec<models::Link>> {
+        let mut stmt = conn
+            .prepare_cached(concat!(select_links!(), "
+                WHERE links.user_id = ?
+                ORDER BY links.created
+            "))?;
+
+        let mut rows = stmt
+            .query(rusqlite::params![user_id])?;
+
+        let mut links = Vec::new();
+        while let Some(row) = rows.next()? {
+            links.push(row_parse::<models::Link>(row)?);
+        }
+
+        Ok(links)
+    }}
+
+    db_method! {get_deleted_links(
+        &self,
+        conn,
+        user_id : u32
+    ) -> Result<Vec<models::Link>> {
+        let mut stmt = conn
+            .prepare_cached(concat!(select_links!(), "
+                WHERE links.user_id = ? AND links.deleted IS NOT NULL
+                ORDER BY links.deleted DESC
+            "))?;
+
+        let mut rows = stmt
+            .query(rusqlite::params![user_id])?;
+
+        let mut links = Vec::new();
+        while let Some(row) = rows.next()? {
+            links.push(row_parse::<models::Link>(row)?);
+        }
+
+        Ok(links)
+    }}
+
+    // moves a link to the trash, it can be brought back with restore_link
+    db_method! {delete_link(
+        &self,
+        conn,
+        user_id : u32,
+        link_id : u32
+    ) -> Result<()> {
+        let n = conn
+            .prepare_cached("
+                UPDATE links SET deleted = datetime('now', 'utc')
+                WHERE id = ? AND user_id = ? AND deleted IS NULL
+            ")?
+            .execute(rusqlite::params![link_id, user_id])?;
+
+        if n == 0 {
+            return Err(Error::LinkIdNotFound(link_id))
+        }
+
+        Ok(())
+    }}
+
+    db_method! {restore_link(
+        &self,
+        conn,
+        user_id : u32,
+        link_id : u32
+    ) -> Result<()> {
+        let n = conn
+            .prepare_cached("
+                UPDATE links SET deleted = NULL
+                WHERE id = ? AND user_id = ? AND deleted IS NOT NULL
+            ")?
+            .execute(rusqlite::params![link_id, user_id])?;
+
+        if n == 0 {
+            return Err(Error::LinkIdNotFound(link_id))
+        }
+
+        Ok(())
+    }}
+
+    // permanently removes a link, only links in the trash can be purged
+    db_method! {purge_link(
+        &self,
+        conn,
+        user_id : u32,
+        link_id : u32
+    ) -> Result<()> {
+        let n = conn
+            .prepare_cached("
+                DELETE FROM links
+                WHERE id = ? AND user_id = ? AND deleted IS NOT NULL
+            ")?
+            .execute(rusqlite::params![link_id, user_id])?;
+
+        if n == 0 {
+            return Err(Error::LinkIdNotFound(link_id))
+        }
+
+        Ok(())
+    }}
+
+    // links which haven't been fetched by the archiver, were asked to be
+    // archived again, or whose last fetch is older than stale_before.
+    // requested links come first.
+    db_method! {get_links_to_archive(
+        &self,
+        conn,
+        stale_before : Option<&models::Time>,
+        limit : u32
+    ) -> Result<Vec<(u32, String)>> {
+        let mut stmt = conn
+            .prepare_cached("
+                SELECT links.id, links.url FROM links
+                WHERE links.deleted IS NULL
+                AND (
+                    links.archive_requested IS NOT NULL
+                    OR NOT EXISTS (
+                        SELECT 1 FROM snapshots
+                        WHERE snapshots.link_id = links.id
+                    )
+                    OR (
+                        SELECT max(snapshots.fetched) FROM snapshots
+                        WHERE snapshots.link_id = links.id
+                    ) < ?
+                )
+                ORDER BY links.archive_requested IS NULL, links.id
+                LIMIT ?
+            ")?;
+
+        let mut rows = stmt
+            .query(rusqlite::params![stale_before, limit])?;
+
+        let mut links = Vec::new();
+        while let Some(row) = rows.next()? {
+            links.push((row.get(0)?, row.get(1)?));
+        }
+
+        Ok(links)
+    }}
+
+    // url is the link's url, which is recorded when the fetch failed. the
+    // reader text replaces the link's text in the search index.
+    db_method! {insert_snapshot(
+        &self,
+        conn,
+        link_id : u32,
+        url : &str,
+        fetched : std::result::Result<archiver::Fetched, String>,
+        processed : archiver::Processed
+    ) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+
+        let insert_blob = |data : &[u8]| -> Result<String> {
+            let digest = archiver::digest(data);
+
+            tx
+                .prepare_cached("
+                    INSERT INTO blobs (digest, data) VALUES (?, ?)
+                    ON CONFLICT (digest) DO NOTHING
+                ")?
+                .execute(rusqlite::params![digest, data])?;
+
+            Ok(digest)
+        };
+
+        match fetched {
+            Ok(fetched) => {
+                let digest = insert_blob(&fetched.body)?;
+                let inlined_digest = processed.inlined
+                    .map(|inlined| insert_blob(inlined.as_bytes()))
+                    .transpose()?;
+
+                let reader = processed.reader.as_ref();
+                let reader_digest = reader
+                    .map(|reader| insert_blob(reader.html.as_bytes()))
+                    .transpose()?;
+                let text_digest = reader
+                    .map(|reader| insert_blob(reader.text.as_bytes()))
+                    .transpose()?;
+
+                tx
+                    .prepare_cached("
+                        INSERT INTO snapshots (
+                            link_id,
+                            url,
+                            status,
+                            headers,
+                            content_type,
+                            digest,
+                            inlined_digest,
+                            reader_digest,
+                            text_digest,
+                            words
+                        )
+                        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    ")?
+                    .execute(rusqlite::params![
+                        link_id,
+                        fetched.url,
+                        fetched.status,
+                        serde_json::to_string(&fetched.headers)?,
+                        fetched.content_type,
+                        digest,
+                        inlined_digest,
+                        reader_digest,
+                        text_digest,
+                        reader.map(|reader| reader.words),
+                    ])?;
+
+                if let Some(reader) = reader {
+                    tx
+                        .prepare_cached("
+                            UPDATE links_fts SET text = ? WHERE rowid = ?
+                        ")?
+                        .execute(rusqlite::params![reader.text, link_id])?;
+                }
+            },
+            Err(err) => {
+                tx
+                    .prepare_cached("
+                        INSERT INTO snapshots (link_id, url, error)
+                        VALUES (?, ?, ?)
+                    ")?
+                    .execute(rusqlite::params![link_id, url, err])?;
+            },
+        }
+
+        tx
+            .prepare_cached("
+                UPDATE links SET archive_requested = NULL WHERE id = ?
+            ")?
+            .execute(rusqlite::params![link_id])?;
+
+        tx.commit()?;
+
+        Ok(())
+    }}
+
+    // replaces the metadata extracted from the link's page
+    db_method! {update_metadata(
+        &self,
+        conn,
+        link_id : u32,
+        meta : &metadata::Metadata
+    ) -> Result<()> {
+        conn
+            .prepare_cached("
+                UPDATE links SET
+                    page_title = ?,
+                    page_description = ?,
+                    image = ?,
+                    site_name = ?,
+                    canonical_url = ?,
+                    author = ?,
+                    published = ?,
+                    favicon = ?
+                WHERE id = ?
+            ")?
+            .execute(rusqlite::params![
+                meta.title,
+                meta.description,
+                meta.image,
+                meta.site_name,
+                meta.canonical_url,
+                meta.author,
+                meta.published,
+                meta.favicon,
+                link_id,
+            ])?;
+
+        Ok(())
+    }}
+
+    // links which have never been checked or are due to be checked again,
+    // along with how many checks in a row have failed
+    db_method! {get_links_to_check(
+        &self,
+        conn,
+        limit : u32
+    ) -> Result<Vec<(u32, String, u32)>> {
+        let mut stmt = conn
+            .prepare_cached("
+                SELECT links.id, links.url, coalesce(link_checks.failures, 0)
+                FROM links
+                LEFT JOIN link_checks ON link_checks.link_id = links.id
+                WHERE links.deleted IS NULL
+                AND (
+                    link_checks.link_id IS NULL
+                    OR link_checks.next_check <= datetime('now', 'utc')
+                )
+                ORDER BY link_checks.next_check IS NOT NULL,
+                    link_checks.next_check
+                LIMIT ?
+            ")?;
+
+        let mut rows = stmt.query(rusqlite::params![limit])?;
+
+        let mut links = Vec::new();
+        while let Some(row) = rows.next()? {
+            links.push((row.get(0)?, row.get(1)?, row.get(2)?));
+        }
+
+        Ok(links)
+    }}
+
+    db_method! {insert_check(
+        &self,
+        conn,
+        link_id : u32,
+        check : &checker::Check,
+        failures : u32,
+        next_check : &models::Time
+    ) -> Result<()> {
+        conn
+            .prepare_cached("
+                INSERT INTO link_checks
+                    (link_id, status, location, error, failures, next_check)
+                VALUES (?, ?, ?, ?, ?, ?)
+                ON CONFLICT (link_id) DO UPDATE SET
+                    checked = datetime('now', 'utc'),
+                    status = excluded.status,
+                    location = excluded.location,
+                    error = excluded.error,
+                    failures = excluded.failures,
+                    next_check = excluded.next_check
+            ")?
+            .execute(rusqlite::params![
+                link_id,
+                check.status,
+                check.location,
+                check.error,
+                failures,
+                next_check,
+            ])?;
+
+        Ok(())
+    }}
+
+    // queues the link to be archived again
+    db_method! {request_archive(
+        &self,
+        conn,
+        user_id : u32,
+        link_id : u32
+    ) -> Result<()> {
+        let n = conn
+            .prepare_cached("
+                UPDATE links SET archive_requested = datetime('now', 'utc')
+                WHERE id = ? AND user_id = ? AND deleted IS NULL
+            ")?
+            .execute(rusqlite::params![link_id, user_id])?;
+
+        if n == 0 {
+            return Err(Error::LinkIdNotFound(link_id))
+        }
+
+        Ok(())
+    }}
+
+    // every fetch of the link, including failed ones, newest first
+    db_method! {get_snapshots(
+        &self,
+        conn,
+        user_id : u32,
+        link_id : u32
+    ) -> Result<Vec<models::Snapshot>> {
+        let mut stmt = conn
+            .prepare_cached("
+                SELECT snapshots.* FROM snapshots
+                JOIN links ON links.id = snapshots.link_id
+                WHERE snapshots.link_id = ? AND links.user_id = ?
+                ORDER BY snapshots.fetched DESC, snapshots.id DESC
+            ")?;
+
+        let mut rows = stmt.query(rusqlite::params![link_id, user_id])?;
+
+        let mut snapshots = Vec::new();
+        while let Some(row) = rows.next()? {
+            snapshots.push(row_parse(row)?);
+        }
+
+        Ok(snapshots)
+    }}
+
+    db_method! {get_snapshot(
+        &self,
+        conn,
+        user_id : u32,
+        link_id : u32,
+        snapshot_id : u32
+    ) -> Result<models::Snapshot> {
+        let mut stmt = conn
+            .prepare_cached("
+                SELECT snapshots.* FROM snapshots
+                JOIN links ON links.id = snapshots.link_id
+                WHERE snapshots.id = ? AND snapshots.link_id = ?
+                AND links.user_id = ?
+            ")?;
+
+        let mut rows = stmt
+            .query(rusqlite::params![snapshot_id, link_id, user_id])?;
+
+        let row = rows.next()?
+            .ok_or(Error::SnapshotNotFound(link_id))?;
 
-fn error_code_match(
-    err : &rusqlite::Error,
-    code : ffi::ErrorCode,
-    ext : i64,
-) -> bool {
-    matches!(
-            err,
-            rusqlite::Error::SqliteFailure(e, _)
-                if e.code == code
-                && i64::from(e.extended_code) == ext)
-}
+        row_parse(row)
+    }}
 
-macro_rules! db_method {
-    ($name:ident (
-        &$self:ident,
-        $conn:ident,
-        $($pname:ident : $ptype:ty),*
-    ) -> $ret:ty $body:block ) => {
-        pub async fn $name (&$self, $( $pname : $ptype, )* ) -> $ret {
-            let $conn = $self.conn.lock().await;
-            tokio::task::block_in_place(|| $body)
+    db_method! {get_latest_snapshot(
+        &self,
+        conn,
+        user_id : u32,
+        link_id : u32
+    ) -> Result<models::Snapshot> {
+        let mut stmt = conn
+            .prepare_cached("
+                SELECT snapshots.* FROM snapshots
+                JOIN links ON links.id = snapshots.link_id
+                WHERE snapshots.link_id = ? AND links.user_id = ?
+                AND snapshots.digest IS NOT NULL
+                ORDER BY snapshots.fetched DESC, snapshots.id DESC
+                LIMIT 1
+            ")?;
+
+        let mut rows = stmt.query(rusqlite::params![link_id, user_id])?;
+
+        let row = rows.next()?
+            .ok_or(Error::SnapshotNotFound(link_id))?;
+
+        row_parse(row)
+    }}
+
+    // successful fetches of all the user's links, oldest first
+    db_method! {get_user_snapshots(
+        &self,
+        conn,
+        user_id : u32
+    ) -> Result<Vec<models::Snapshot>> {
+        let mut stmt = conn
+            .prepare_cached("
+                SELECT snapshots.* FROM snapshots
+                JOIN links ON links.id = snapshots.link_id
+                WHERE links.user_id = ?
+                AND snapshots.digest IS NOT NULL
+                ORDER BY snapshots.fetched, snapshots.id
+            ")?;
+
+        let mut rows = stmt.query(rusqlite::params![user_id])?;
+
+        let mut snapshots = Vec::new();
+        while let Some(row) = rows.next()? {
+            snapshots.push(row_parse(row)?);
         }
-    }
-}
 
-pub struct Db {
-    conn : Mutex<Connection>,
-}
+        Ok(snapshots)
+    }}
 
-impl Db {
-    pub fn new<P : AsRef<std::path::Path>>(p : P) -> Result<Self> {
-        let conn = Connection::open(p)?;
+    db_method! {get_blob(&self, conn, digest : &str) -> Result<Vec<u8>> {
+        let mut stmt = conn
+            .prepare_cached("SELECT data FROM blobs WHERE digest = ?")?;
 
-        conn.pragma_update(None, "foreign_keys", &"ON")?;
+        let mut rows = stmt.query(rusqlite::params![digest])?;
 
-        Ok(Self {
-            conn : Mutex::new(conn),
-        })
-    }
+        let row = rows.next()?
+            .ok_or(Error::Internal)?;
 
-    db_method! {insert_user(
+        Ok(row.get(0)?)
+    }}
+
+    db_method! {get_tags(
         &self,
         conn,
-        name : &str,
-        password : &str
+        user_id : u32
+    ) -> Result<Vec<models::Tag>> {
+        let mut stmt = conn
+            .prepare_cached("
+                SELECT tags.name, count(links.id) AS links
+                FROM tags
+                LEFT JOIN link_tags ON link_tags.tag_id = tags.id
+                LEFT JOIN links
+                    ON links.id = link_tags.link_id
+                    AND links.deleted IS NULL
+                WHERE tags.user_id = ?
+                GROUP BY tags.id
+                ORDER BY tags.name
+            ")?;
+
+        let mut rows = stmt
+            .query(rusqlite::params![user_id])?;
+
+        let mut tags = Vec::new();
+        while let Some(row) = rows.next()? {
+            tags.push(row_parse::<models::Tag>(row)?);
+        }
+
+        Ok(tags)
+    }}
+
+    // tags the link, creating the tag if the user doesn't have it yet
+    db_method! {add_tag(
+        &self,
+        conn,
+        user_id : u32,
+        link_id : u32,
+        tag : &str
     ) -> Result<()> {
         conn
-            .prepare_cached("INSERT INTO users (name, password) VALUES (?, ?)")?
-            .execute(rusqlite::params![name, password])
+            .prepare_cached("
+                INSERT INTO tags (user_id, name) VALUES (?, ?)
+                ON CONFLICT (user_id, name) DO NOTHING
+            ")?
+            .execute(rusqlite::params![user_id, tag])?;
+
+        let n = conn
+            .prepare_cached("
+                INSERT INTO link_tags (link_id, tag_id)
+                SELECT links.id, tags.id
+                FROM links JOIN tags ON tags.user_id = links.user_id
+                WHERE links.id = ? AND links.user_id = ? AND tags.name = ?
+                ON CONFLICT DO NOTHING
+            ")?
+            .execute(rusqlite::params![link_id, user_id, tag])?;
+
+        // either the link doesn't exist or it already has the tag
+        if n == 0 && !conn
+            .prepare_cached("SELECT id FROM links WHERE id = ? AND user_id = ?")?
+            .exists(rusqlite::params![link_id, user_id])?
+        {
+            return Err(Error::LinkIdNotFound(link_id))
+        }
+
+        Ok(())
+    }}
+
+    db_method! {insert_collection(
+        &self,
+        conn,
+        user_id : u32,
+        name : &str,
+        visibility : models::Visibility
+    ) -> Result<u32> {
+        let mut bytes = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let slug = hashing::hex(&bytes);
+
+        conn
+            .prepare_cached("
+                INSERT INTO collections (user_id, name, visibility, slug)
+                VALUES (?, ?, ?, ?)
+            ")?
+            .execute(rusqlite::params![user_id, name, visibility, slug])
             .map_err(|err| {
                 if error_code_match(
                     &err,
@@ -65,98 +1671,244 @@ impl Db {
                     err.into()
                 }
             })?;
-        Ok(())
+
+        Ok(conn.last_insert_rowid() as u32)
     }}
 
-    db_method! {upsert_user(
+    // visibility limits the collections to ones which are that visible
+    db_method! {get_collections(
         &self,
         conn,
-        name : &str
-    ) -> Result<models::User> {
+        user_id : u32,
+        visibility : Option<models::Visibility>
+    ) -> Result<Vec<models::Collection>> {
         let mut stmt = conn
-            .prepare_cached("
-                INSERT INTO users (name)
-                VALUES (?)
-                ON CONFLICT (name)
-                DO UPDATE SET name = name
-                RETURNING *
-            ")?;
+            .prepare_cached(concat!(select_collections!(), "
+                WHERE collections.user_id = ?
+                AND (? IS NULL OR collections.visibility = ?)
+                GROUP BY collections.id
+                ORDER BY collections.name
+            "))?;
 
-        let mut rows = stmt.query(rusqlite::params![name])?;
+        let mut rows = stmt
+            .query(rusqlite::params![user_id, visibility, visibility])?;
 
-        let row = rows.next()?.unwrap();
+        let mut collections = Vec::new();
+        while let Some(row) = rows.next()? {
+            collections.push(row_parse::<models::Collection>(row)?);
+        }
 
-        Ok(row_parse(row)?)
+        Ok(collections)
     }}
 
-    db_method! {get_user(&self, conn, user_id : u32) -> Result<models::User> {
+    db_method! {get_collection(
+        &self,
+        conn,
+        user_id : u32,
+        collection_id : u32
+    ) -> Result<models::Collection> {
         let mut stmt = conn
-            .prepare_cached("SELECT * FROM users WHERE users.id = ?")?;
+            .prepare_cached(concat!(select_collections!(), "
+                WHERE collections.id = ? AND collections.user_id = ?
+                GROUP BY collections.id
+            "))?;
 
-        let mut rows = stmt.query(rusqlite::params![user_id])?;
+        let mut rows = stmt.query(rusqlite::params![collection_id, user_id])?;
 
         let row = rows.next()?
-            .ok_or(Error::UserIdNotFound(user_id))?;
+            .ok_or(Error::CollectionIdNotFound(collection_id))?;
 
-        Ok(row_parse(row)?)
+        row_parse(row)
     }}
 
-    db_method! {get_user_by_name(
+    // private collections can only be found by their owner, through
+    // get_collection. the collections of disabled users aren't found.
+    db_method! {get_shared_collection(
         &self,
         conn,
-        username : &str
-    ) -> Result<models::User> {
+        slug : &str
+    ) -> Result<models::Collection> {
         let mut stmt = conn
-            .prepare_cached("SELECT * FROM users WHERE users.name = ?")?;
+            .prepare_cached(concat!(select_collections!(), "
+                WHERE collections.slug = ?
+                AND collections.visibility != 'private'
+                AND collections.user_id IN (
+                    SELECT id FROM users WHERE deleted IS NULL
+                )
+                GROUP BY collections.id
+            "))?;
 
-        let mut rows = stmt.query(rusqlite::params![username])?;
+        let mut rows = stmt.query(rusqlite::params![slug])?;
 
         let row = rows.next()?
-            .ok_or(Error::UserNameNotFound(username.to_string()))?;
+            .ok_or_else(|| Error::CollectionSlugNotFound(slug.to_string()))?;
 
-        Ok(row_parse(row)?)
+        row_parse(row)
     }}
 
-    db_method! {insert_link(
+    db_method! {update_collection(
         &self,
         conn,
         user_id : u32,
-        link : &str
+        collection_id : u32,
+        name : &str,
+        visibility : models::Visibility
     ) -> Result<()> {
-        conn
-            .prepare_cached("INSERT INTO links (user_id, url) VALUES (?, ?)")?
-            .execute(rusqlite::params![user_id, link])
+        let n = conn
+            .prepare_cached("
+                UPDATE collections SET name = ?, visibility = ?
+                WHERE id = ? AND user_id = ?
+            ")?
+            .execute(rusqlite::params![
+                name,
+                visibility,
+                collection_id,
+                user_id
+            ])
             .map_err(|err| {
                 if error_code_match(
                     &err,
                     ffi::ErrorCode::ConstraintViolation,
-                    1555
+                    2067
                 ) {
-                    Error::DuplicateUrl(link.to_string())
+                    Error::DuplicateName(name.to_string())
                 } else {
                     err.into()
                 }
             })?;
+
+        if n == 0 {
+            return Err(Error::CollectionIdNotFound(collection_id))
+        }
+
         Ok(())
     }}
 
-    db_method! {get_links(
+    // the links stay, only their membership is removed
+    db_method! {delete_collection(
         &self,
         conn,
-        user_id : u32
-    ) -> Result<Vec<models::Link>> {
+        user_id : u32,
+        collection_id : u32
+    ) -> Result<()> {
+        let n = conn
+            .prepare_cached("
+                DELETE FROM collections WHERE id = ? AND user_id = ?
+            ")?
+            .execute(rusqlite::params![collection_id, user_id])?;
+
+        if n == 0 {
+            return Err(Error::CollectionIdNotFound(collection_id))
+        }
+
+        Ok(())
+    }}
+
+    db_method! {add_collection_link(
+        &self,
+        conn,
+        user_id : u32,
+        collection_id : u32,
+        link_id : u32
+    ) -> Result<()> {
+        let n = conn
+            .prepare_cached("
+                INSERT INTO collection_links (collection_id, link_id)
+                SELECT collections.id, links.id
+                FROM collections JOIN links
+                    ON links.user_id = collections.user_id
+                WHERE collections.id = ? AND links.id = ?
+                AND collections.user_id = ?
+                ON CONFLICT DO NOTHING
+            ")?
+            .execute(rusqlite::params![collection_id, link_id, user_id])?;
+
+        // either one doesn't exist or the link is already in the collection
+        if n == 0 {
+            if !conn
+                .prepare_cached("
+                    SELECT id FROM collections WHERE id = ? AND user_id = ?
+                ")?
+                .exists(rusqlite::params![collection_id, user_id])?
+            {
+                return Err(Error::CollectionIdNotFound(collection_id))
+            }
+
+            if !conn
+                .prepare_cached("
+                    SELECT id FROM links WHERE id = ? AND user_id = ?
+                ")?
+                .exists(rusqlite::params![link_id, user_id])?
+            {
+                return Err(Error::LinkIdNotFound(link_id))
+            }
+        }
+
+        Ok(())
+    }}
+
+    db_method! {remove_collection_link(
+        &self,
+        conn,
+        user_id : u32,
+        collection_id : u32,
+        link_id : u32
+    ) -> Result<()> {
+        conn
+            .prepare_cached("
+                DELETE FROM collection_links
+                WHERE collection_id = (
+                    SELECT id FROM collections WHERE id = ? AND user_id = ?
+                )
+                AND link_id = ?
+            ")?
+            .execute(rusqlite::params![collection_id, user_id, link_id])?;
+
+        Ok(())
+    }}
+
+    // the ids of the collections the link is in
+    db_method! {get_link_collection_ids(
+        &self,
+        conn,
+        user_id : u32,
+        link_id : u32
+    ) -> Result<Vec<u32>> {
         let mut stmt = conn
-            .prepare_cached("SELECT * FROM links WHERE links.user_id = ?")?;
+            .prepare_cached("
+                SELECT collection_links.collection_id
+                FROM collection_links JOIN links
+                    ON links.id = collection_links.link_id
+                WHERE links.id = ? AND links.user_id = ?
+            ")?;
 
-        let mut rows = stmt
-            .query(rusqlite::params![user_id])?;
+        let mut rows = stmt.query(rusqlite::params![link_id, user_id])?;
 
-        let mut links = Vec::new();
+        let mut ids = Vec::new();
         while let Some(row) = rows.next()? {
-            links.push(row_parse::<models::Link>(row)?);
+            ids.push(row.get(0)?);
         }
 
-        Ok(links)
+        Ok(ids)
+    }}
+
+    db_method! {remove_tag(
+        &self,
+        conn,
+        user_id : u32,
+        link_id : u32,
+        tag : &str
+    ) -> Result<()> {
+        conn
+            .prepare_cached("
+                DELETE FROM link_tags
+                WHERE link_id = ? AND tag_id = (
+                    SELECT id FROM tags WHERE user_id = ? AND name = ?
+                )
+            ")?
+            .execute(rusqlite::params![link_id, user_id, tag])?;
+
+        Ok(())
     }}
 }
 
@@ -264,11 +2016,43 @@ macro_rules! impl_from_row {
     }
 
 impl_from_row! {users, models::User {
-    id, name, created, deleted
+    id, name, created, deleted, last_login
+}}
+
+impl_from_row! {users, models::UserSummary {
+    id, name, created, deleted, last_login, workspace, links
 }}
 
 impl_from_row! {links, models::Link {
-    user_id, url, created, deleted
+    id, user_id, url, canonical, title, description, notes, created, deleted,
+    tags, snapshots, last_archived, archive_requested, health, moved_to,
+    checked, page_title, page_description, image, site_name, canonical_url,
+    author, published, favicon, words
+}}
+
+impl_from_row! {snapshots, models::Snapshot {
+    id, link_id, url, fetched, status, headers, content_type, digest,
+    inlined_digest, reader_digest, text_digest, words, error
+}}
+
+impl_from_row! {api_tokens, models::ApiToken {
+    id, user_id, name, created, last_used, revoked
+}}
+
+impl_from_row! {tags, models::Tag {
+    name, links
+}}
+
+impl_from_row! {workspaces, models::Workspace {
+    id, name, created, role
+}}
+
+impl_from_row! {workspace_members, models::Member {
+    user_id, name, role, added
+}}
+
+impl_from_row! {collections, models::Collection {
+    id, user_id, name, visibility, slug, created, links
 }}
 
 impl FromSql for models::Time {
@@ -281,3 +2065,197 @@ impl FromSql for models::Time {
         Ok(dt.assume_offset(time::UtcOffset::UTC).into())
     }
 }
+
+impl ToSql for models::Time {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let s = self
+            .to_offset(time::UtcOffset::UTC)
+            .format(&TIME_FORMAT)
+            .map_err(|err| {
+                rusqlite::Error::ToSqlConversionFailure(Box::new(err))
+            })?;
+
+        Ok(s.into())
+    }
+}
+
+impl FromSql for models::Health {
+    fn column_result(value : ValueRef) -> FromSqlResult<models::Health> {
+        match value.as_str()? {
+            "ok" => Ok(models::Health::Ok),
+            "broken" => Ok(models::Health::Broken),
+            "moved" => Ok(models::Health::Moved),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl ToSql for models::Health {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(match self {
+            models::Health::Ok => "ok",
+            models::Health::Broken => "broken",
+            models::Health::Moved => "moved",
+        }.into())
+    }
+}
+
+impl FromSql for models::Visibility {
+    fn column_result(value : ValueRef) -> FromSqlResult<models::Visibility> {
+        match value.as_str()? {
+            "public" => Ok(models::Visibility::Public),
+            "unlisted" => Ok(models::Visibility::Unlisted),
+            "private" => Ok(models::Visibility::Private),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl ToSql for models::Visibility {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(match self {
+            models::Visibility::Public => "public",
+            models::Visibility::Unlisted => "unlisted",
+            models::Visibility::Private => "private",
+        }.into())
+    }
+}
+
+impl FromSql for models::Role {
+    fn column_result(value : ValueRef) -> FromSqlResult<models::Role> {
+        match value.as_str()? {
+            "owner" => Ok(models::Role::Owner),
+            "editor" => Ok(models::Role::Editor),
+            "viewer" => Ok(models::Role::Viewer),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl ToSql for models::Role {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(match self {
+            models::Role::Owner => "owner",
+            models::Role::Editor => "editor",
+            models::Role::Viewer => "viewer",
+        }.into())
+    }
+}
+
+impl FromSql for models::Tags {
+    fn column_result(value : ValueRef) -> FromSqlResult<models::Tags> {
+        let s : Option<String> = Option::column_result(value)?;
+
+        let tags = s
+            .map(|s| s.split(' ').map(String::from).collect())
+            .unwrap_or_default();
+
+        Ok(models::Tags(tags))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonicals(conn : &Connection) -> Vec<Option<String>> {
+        let mut stmt = conn
+            .prepare("SELECT canonical FROM links ORDER BY id")
+            .unwrap();
+        let rows = stmt.query_map(rusqlite::params![], |row| row.get(0));
+
+        rows.unwrap().map(|c| c.unwrap()).collect()
+    }
+
+    #[test]
+    fn canonicalize_links_once_per_config() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+
+        conn.execute_batch("
+            INSERT INTO users (name) VALUES ('julio');
+            INSERT INTO links (user_id, url) VALUES
+                (1, 'https://ear7h.net/a'),
+                (1, 'http://ear7h.net/a/'),
+                (1, 'https://ear7h.net/b?utm_source=x');
+        ").unwrap();
+
+        let mut config = canonical::Config::default();
+        canonicalize_links(&mut conn, &config).unwrap();
+        assert_eq!(canonicals(&conn), vec![
+            Some("https://ear7h.net/a".to_string()),
+            None,
+            Some("https://ear7h.net/b".to_string()),
+        ]);
+
+        // the same config leaves the links alone
+        conn.execute("UPDATE links SET canonical = 'x' WHERE id = 3", [])
+            .unwrap();
+        canonicalize_links(&mut conn, &config).unwrap();
+        assert_eq!(canonicals(&conn)[2].as_deref(), Some("x"));
+
+        config.https = false;
+        config.strip_params.clear();
+        canonicalize_links(&mut conn, &config).unwrap();
+        assert_eq!(canonicals(&conn), vec![
+            Some("https://ear7h.net/a".to_string()),
+            Some("http://ear7h.net/a".to_string()),
+            Some("https://ear7h.net/b?utm_source=x".to_string()),
+        ]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn workspace_names_leave_user_names_free() {
+        let db = Db::new(":memory:", &canonical::Config::default()).unwrap();
+
+        let owner = db.upsert_user("julio", true).await.unwrap();
+        let workspace_id = db.insert_workspace(owner.id, "ana").await.unwrap();
+
+        // the person named like the workspace can still register and log in
+        let ana = db.upsert_user("ana", true).await.unwrap();
+        assert_ne!(ana.id, workspace_id);
+        let again = db.upsert_user("ana", false).await.unwrap();
+        assert_eq!(again.id, ana.id);
+
+        let workspace = db.get_workspace(workspace_id, owner.id).await.unwrap();
+        assert_eq!(workspace.name, "ana");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn shared_collections_hidden_when_disabled() {
+        let db = Db::new(":memory:", &canonical::Config::default()).unwrap();
+
+        let user = db.upsert_user("julio", true).await.unwrap();
+        let collection_id = db
+            .insert_collection(user.id, "reading", models::Visibility::Public)
+            .await
+            .unwrap();
+        let collection = db.get_collection(user.id, collection_id).await;
+        let slug = collection.unwrap().slug;
+        assert!(db.get_shared_collection(&slug).await.is_ok());
+
+        db.set_user_disabled(user.id, true).await.unwrap();
+        assert!(matches!(
+            db.get_shared_collection(&slug).await,
+            Err(Error::CollectionSlugNotFound(_)),
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn saving_trashed_link_restores_it() {
+        let db = Db::new(":memory:", &canonical::Config::default()).unwrap();
+        let url = "https://ear7h.net/a";
+
+        let user = db.upsert_user("julio", true).await.unwrap();
+        let link_id = db.insert_link(user.id, url, url, None).await.unwrap();
+        db.delete_link(user.id, link_id).await.unwrap();
+
+        assert!(matches!(
+            db.insert_link(user.id, url, url, None).await,
+            Err(Error::DuplicateUrl(_, id)) if id == link_id,
+        ));
+        let link = db.get_link(user.id, link_id).await.unwrap();
+        assert!(link.deleted.is_none());
+        assert!(db.get_deleted_links(user.id).await.unwrap().is_empty());
+    }
+}
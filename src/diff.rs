@@ -0,0 +1,228 @@
+//! line based diffs between the text of two snapshots, using myers'
+//! algorithm
+
+use serde::Serialize;
+
+/// diffs with more edits than this aren't worth reading, the whole text is
+/// shown as replaced instead
+const MAX_EDITS : usize = 4000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "op", content = "line", rename_all = "lowercase")]
+pub enum Change<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// the changes which turn old into new
+pub fn lines<'a>(old : &'a str, new : &'a str) -> Vec<Change<'a>> {
+    let a = old.lines().collect::<Vec<_>>();
+    let b = new.lines().collect::<Vec<_>>();
+
+    // the common prefix and suffix are usually most of the page
+    let prefix = a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    let mut changes = a[..prefix]
+        .iter()
+        .map(|line| Change::Same(line))
+        .collect::<Vec<_>>();
+
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+
+    match myers(a_mid, b_mid) {
+        Some(mid) => changes.extend(mid),
+        None => {
+            changes.extend(a_mid.iter().map(|line| Change::Removed(line)));
+            changes.extend(b_mid.iter().map(|line| Change::Added(line)));
+        },
+    }
+
+    changes.extend(a[a.len() - suffix..].iter().map(|line| Change::Same(line)));
+    changes
+}
+
+/// the shortest edit script from a to b, none if it's longer than MAX_EDITS
+fn myers<'a>(a : &[&'a str], b : &[&'a str]) -> Option<Vec<Change<'a>>> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = (a.len() + b.len()).min(MAX_EDITS) as isize;
+
+    // v[k] is the furthest x reached on diagonal k = x - y, offset so k can
+    // be negative. trace[d] holds v[-d..=d] from before step d.
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    let mut trace : Vec<Vec<isize>> = Vec::new();
+
+    let mut found = false;
+    'outer: for d in 0..=max {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+
+        for k in (-d..=d).step_by(2) {
+            let i = (offset + k) as usize;
+
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1]
+            } else {
+                v[i - 1] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+
+            v[i] = x;
+
+            if x >= n && y >= m {
+                found = true;
+                break 'outer
+            }
+        }
+    }
+
+    if !found {
+        return None
+    }
+
+    // walk back from the end through the saved states
+    let mut changes = Vec::new();
+    let (mut x, mut y) = (n, m);
+
+    for d in (0..trace.len() as isize).rev() {
+        if d == 0 {
+            while x > 0 && y > 0 {
+                changes.push(Change::Same(a[x as usize - 1]));
+                x -= 1;
+                y -= 1;
+            }
+            break
+        }
+
+        let v = &trace[d as usize];
+        let at = |k : isize| v[(k + d) as usize];
+
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            changes.push(Change::Same(a[x as usize - 1]));
+            x -= 1;
+            y -= 1;
+        }
+
+        if x == prev_x {
+            changes.push(Change::Added(b[y as usize - 1]));
+        } else {
+            changes.push(Change::Removed(a[x as usize - 1]));
+        }
+
+        x = prev_x;
+        y = prev_y;
+    }
+
+    changes.reverse();
+    Some(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Change::*;
+    use super::*;
+
+    /// applies the changes to old, checking they're consistent with it
+    fn apply<'a>(old : &str, changes : &[Change<'a>]) -> Vec<&'a str> {
+        let mut old = old.lines();
+        let mut out = Vec::new();
+
+        for change in changes {
+            match change {
+                Same(line) => {
+                    assert_eq!(old.next(), Some(*line));
+                    out.push(*line);
+                },
+                Removed(line) => assert_eq!(old.next(), Some(*line)),
+                Added(line) => out.push(*line),
+            }
+        }
+
+        assert_eq!(old.next(), None);
+        out
+    }
+
+    fn edits(changes : &[Change]) -> usize {
+        changes.iter().filter(|c| !matches!(c, Same(_))).count()
+    }
+
+    #[test]
+    fn identical() {
+        assert_eq!(lines("a\nb", "a\nb"), vec![Same("a"), Same("b")]);
+        assert_eq!(lines("", ""), vec![]);
+    }
+
+    #[test]
+    fn additions_and_removals() {
+        assert_eq!(lines("", "a\nb"), vec![Added("a"), Added("b")]);
+        assert_eq!(lines("a\nb", ""), vec![Removed("a"), Removed("b")]);
+        assert_eq!(lines("a\nc", "a\nb\nc"), vec![
+            Same("a"),
+            Added("b"),
+            Same("c")
+        ]);
+        assert_eq!(lines("a\nb\nc", "a\nc"), vec![
+            Same("a"),
+            Removed("b"),
+            Same("c")
+        ]);
+    }
+
+    #[test]
+    fn replaced_line() {
+        assert_eq!(lines("a\nb\nc", "a\nx\nc"), vec![
+            Same("a"),
+            Removed("b"),
+            Added("x"),
+            Same("c"),
+        ]);
+    }
+
+    #[test]
+    fn shortest_edit_script() {
+        // the classic example from myers' paper, 5 edits apart
+        let old = "a\nb\nc\na\nb\nb\na";
+        let new = "c\nb\na\nb\na\nc";
+
+        let changes = lines(old, new);
+        assert_eq!(apply(old, &changes), new.lines().collect::<Vec<_>>());
+        assert_eq!(edits(&changes), 5);
+    }
+
+    #[test]
+    fn too_many_edits() {
+        let old = (0..MAX_EDITS)
+            .map(|i| format!("a{}\n", i))
+            .collect::<String>();
+        let new = (0..MAX_EDITS)
+            .map(|i| format!("b{}\n", i))
+            .collect::<String>();
+
+        let changes = lines(&old, &new);
+        assert_eq!(apply(&old, &changes), new.lines().collect::<Vec<_>>());
+        assert!(changes[..MAX_EDITS].iter().all(|c| matches!(c, Removed(_))));
+        assert!(changes[MAX_EDITS..].iter().all(|c| matches!(c, Added(_))));
+    }
+}
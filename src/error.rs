@@ -6,12 +6,36 @@ pub type Result<T> = std::result::Result<T, Error>;
 #[derive(QuickFrom, Debug)]
 pub enum Error {
     InvalidUrl(String),
-    DuplicateUrl(String),
+    InvalidTag(String),
+    /// the url and id of the link which is already saved
+    DuplicateUrl(String, u32),
     DuplicateName(String),
     TokenDurationTooBig,
     UserNameNotFound(String),
     UserIdNotFound(u32),
+    LinkIdNotFound(u32),
+    ApiTokenIdNotFound(u32),
+    SnapshotNotFound(u32),
+    CollectionIdNotFound(u32),
+    CollectionSlugNotFound(String),
+    WorkspaceIdNotFound(u32),
+    /// a workspace must keep at least one owner
+    LastOwner,
+    FetchFailed(String),
+    UnknownMigration(String),
     FailedLogin,
+    /// the user was disabled by an admin
+    UserDisabled(String),
+    /// the name isn't allowed to register
+    RegistrationClosed(String),
+    /// a form post's csrf token is missing or wasn't made for the session
+    CsrfMismatch,
+    /// logins are locked out for this many more seconds
+    TooManyAttempts(u64),
+    /// the json api was sent a body which isn't json
+    UnsupportedMediaType,
+    /// the request body is bigger than the server reads
+    PayloadTooLarge,
     Unauthorized,
     BadRequest,
     RouteNotFound,
@@ -36,6 +60,50 @@ pub enum Error {
     Authn(authn::client::Error),
 }
 
+impl Error {
+    /// a stable identifier for the kind of error, reported by the json api so
+    /// clients don't have to match on messages
+    pub fn code(&self) -> &'static str {
+        use Error::*;
+
+        match self {
+            InvalidUrl(_) => "invalid_url",
+            InvalidTag(_) => "invalid_tag",
+            DuplicateUrl(_, _) => "duplicate_url",
+            DuplicateName(_) => "duplicate_name",
+            TokenDurationTooBig => "token_duration_too_big",
+            UserNameNotFound(_) => "user_name_not_found",
+            UserIdNotFound(_) => "user_id_not_found",
+            LinkIdNotFound(_) => "link_id_not_found",
+            ApiTokenIdNotFound(_) => "api_token_id_not_found",
+            SnapshotNotFound(_) => "snapshot_not_found",
+            CollectionIdNotFound(_) => "collection_id_not_found",
+            CollectionSlugNotFound(_) => "collection_slug_not_found",
+            WorkspaceIdNotFound(_) => "workspace_id_not_found",
+            LastOwner => "last_owner",
+            FetchFailed(_) => "fetch_failed",
+            UnknownMigration(_) => "unknown_migration",
+            FailedLogin => "failed_login",
+            UserDisabled(_) => "user_disabled",
+            RegistrationClosed(_) => "registration_closed",
+            CsrfMismatch => "csrf_mismatch",
+            TooManyAttempts(_) => "too_many_attempts",
+            UnsupportedMediaType => "unsupported_media_type",
+            PayloadTooLarge => "payload_too_large",
+            Unauthorized => "unauthorized",
+            BadRequest => "bad_request",
+            RouteNotFound => "route_not_found",
+            Internal => "internal",
+            Sqlite(_) => "sqlite",
+            Time(_) => "time",
+            Hyper(_) => "hyper",
+            Json(_) => "json",
+            Io(_) => "io",
+            Authn(_) => "authn",
+        }
+    }
+}
+
 impl From<MuxError> for Error {
     fn from(err : MuxError) -> Self {
         use MuxError::*;
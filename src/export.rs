@@ -0,0 +1,92 @@
+//! formats for exporting all of a user's links, each is written one link at a
+//! time so the export can be streamed
+
+use crate::time_utils::TIME_FORMAT;
+use crate::{bookmarks, models};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    /// netscape bookmark file, which browsers can import
+    Html,
+    /// json lines, one link object per line
+    Json,
+    Csv,
+}
+
+const CSV_HEADER : &str =
+    "id,url,title,description,notes,tags,created,deleted\r\n";
+
+fn csv_field(s : &str) -> String {
+    if s.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn csv_time(t : &models::Time) -> String {
+    t.format(&TIME_FORMAT).unwrap()
+}
+
+impl Format {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Html => "text/html; charset=utf-8",
+            Format::Json => "application/x-ndjson",
+            Format::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Format::Html => "links.html",
+            Format::Json => "links.jsonl",
+            Format::Csv => "links.csv",
+        }
+    }
+
+    pub fn header(&self) -> &'static str {
+        match self {
+            Format::Html => bookmarks::HEADER,
+            Format::Json => "",
+            Format::Csv => CSV_HEADER,
+        }
+    }
+
+    pub fn footer(&self) -> &'static str {
+        match self {
+            Format::Html => bookmarks::FOOTER,
+            Format::Json | Format::Csv => "",
+        }
+    }
+
+    pub fn entry(&self, link : &models::Link) -> String {
+        match self {
+            // browsers have no notion of deleted bookmarks, so the trash is
+            // left out rather than brought back to life on import
+            Format::Html if link.deleted.is_some() => String::new(),
+            Format::Html => bookmarks::entry(link),
+            Format::Json => {
+                let mut s = serde_json::to_string(link).unwrap();
+                s.push('\n');
+                s
+            },
+            Format::Csv => {
+                let fields = [
+                    link.id.to_string(),
+                    csv_field(&link.url),
+                    csv_field(link.title.as_deref().unwrap_or("")),
+                    csv_field(link.description.as_deref().unwrap_or("")),
+                    csv_field(link.notes.as_deref().unwrap_or("")),
+                    csv_field(&link.tags.0.join(" ")),
+                    csv_time(&link.created),
+                    link.deleted.as_ref().map(csv_time).unwrap_or_default(),
+                ];
+
+                let mut s = fields.join(",");
+                s.push_str("\r\n");
+                s
+            },
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! atom and rss feeds of links, so they can be followed from feed readers
+
+use crate::html::escape;
+use crate::models;
+use crate::time_utils::{HTTP_DATE_FORMAT, RFC3339_FORMAT};
+
+/// how many of the newest links are in a feed
+pub const SIZE : u32 = 50;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    Atom,
+    Rss,
+}
+
+pub struct Feed<'a> {
+    pub title :   String,
+    /// the server's host name, entry ids are tag uris under it
+    pub host :    &'a str,
+    /// the feed's own url
+    pub url :     String,
+    /// the html page with the same links
+    pub page :    String,
+    pub author :  &'a str,
+    /// newest first
+    pub links :   &'a [models::Link],
+    /// when the feed's links last changed
+    pub updated : &'a models::Time,
+}
+
+fn atom_time(t : &models::Time) -> String {
+    t.format(&RFC3339_FORMAT).unwrap()
+}
+
+fn rss_time(t : &models::Time) -> String {
+    t.format(&HTTP_DATE_FORMAT).unwrap()
+}
+
+fn title(link : &models::Link) -> &str {
+    link.title
+        .as_deref()
+        .or(link.page_title.as_deref())
+        .unwrap_or(&link.url)
+}
+
+fn summary(link : &models::Link) -> Option<&str> {
+    link.description
+        .as_deref()
+        .or(link.page_description.as_deref())
+}
+
+impl<'a> Feed<'a> {
+    /// a tag uri, so the id doesn't change when the link is edited
+    fn entry_id(&self, link : &models::Link) -> String {
+        format!("tag:{},2021:links/{}", self.host, link.id)
+    }
+}
+
+impl Format {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Atom => "application/atom+xml; charset=utf-8",
+            Format::Rss => "application/rss+xml; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Atom => "atom",
+            Format::Rss => "rss",
+        }
+    }
+
+    pub fn render(&self, feed : &Feed) -> String {
+        match self {
+            Format::Atom => atom(feed),
+            Format::Rss => rss(feed),
+        }
+    }
+}
+
+fn atom(feed : &Feed) -> String {
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>
+<feed xmlns=\"http://www.w3.org/2005/Atom\">
+<title>{title}</title>
+<link rel=\"self\" href=\"{url}\"/>
+<link rel=\"alternate\" type=\"text/html\" href=\"{page}\"/>
+<id>{url}</id>
+<updated>{updated}</updated>
+<author><name>{author}</name></author>
+",
+        title = escape(&feed.title),
+        url = escape(&feed.url),
+        page = escape(&feed.page),
+        updated = atom_time(feed.updated),
+        author = escape(feed.author),
+    );
+
+    for link in feed.links {
+        out.push_str(&format!(
+            "<entry>
+<title>{title}</title>
+<link rel=\"alternate\" href=\"{url}\"/>
+<id>{id}</id>
+<published>{created}</published>
+<updated>{created}</updated>
+",
+            title = escape(title(link)),
+            url = escape(&link.url),
+            id = escape(&feed.entry_id(link)),
+            created = atom_time(&link.created),
+        ));
+
+        if let Some(summary) = summary(link) {
+            out.push_str(&format!("<summary>{}</summary>\n", escape(summary)));
+        }
+
+        for tag in link.tags.0.iter() {
+            out.push_str(&format!("<category term=\"{}\"/>\n", escape(tag)));
+        }
+
+        out.push_str("</entry>\n");
+    }
+
+    out.push_str("</feed>\n");
+    out
+}
+
+fn rss(feed : &Feed) -> String {
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>
+<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">
+<channel>
+<title>{title}</title>
+<link>{page}</link>
+<description>{title}</description>
+<atom:link rel=\"self\" type=\"application/rss+xml\" href=\"{url}\"/>
+<lastBuildDate>{updated}</lastBuildDate>
+",
+        title = escape(&feed.title),
+        page = escape(&feed.page),
+        url = escape(&feed.url),
+        updated = rss_time(feed.updated),
+    );
+
+    for link in feed.links {
+        out.push_str(&format!(
+            "<item>
+<title>{title}</title>
+<link>{url}</link>
+<guid isPermaLink=\"false\">{id}</guid>
+<pubDate>{created}</pubDate>
+",
+            title = escape(title(link)),
+            url = escape(&link.url),
+            id = escape(&feed.entry_id(link)),
+            created = rss_time(&link.created),
+        ));
+
+        if let Some(summary) = summary(link) {
+            out.push_str(&format!(
+                "<description>{}</description>\n",
+                escape(summary),
+            ));
+        }
+
+        for tag in link.tags.0.iter() {
+            out.push_str(&format!("<category>{}</category>\n", escape(tag)));
+        }
+
+        out.push_str("</item>\n");
+    }
+
+    out.push_str("</channel>\n</rss>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    fn time(t : time::OffsetDateTime) -> models::Time {
+        t.into()
+    }
+
+    fn link(id : u32, url : &str) -> models::Link {
+        models::Link {
+            id,
+            user_id :           1,
+            url :               url.to_string(),
+            canonical :         None,
+            title :             None,
+            description :       None,
+            notes :             None,
+            created :           time(datetime!(2021-09-30 12:00 UTC)),
+            deleted :           None,
+            tags :              models::Tags(Vec::new()),
+            snapshots :         0,
+            last_archived :     None,
+            archive_requested : None,
+            health :            None,
+            moved_to :          None,
+            checked :           None,
+            page_title :        None,
+            page_description :  None,
+            image :             None,
+            site_name :         None,
+            canonical_url :     None,
+            author :            None,
+            published :         None,
+            favicon :           None,
+            words :             None,
+        }
+    }
+
+    fn render(format : Format, links : &[models::Link]) -> String {
+        let updated = time(datetime!(2021-10-01 08:30:05 UTC));
+
+        format.render(&Feed {
+            title :   "julio's <links>".to_string(),
+            host :    "links.ear7h.net",
+            url :     "https://links.ear7h.net/users/1/links.atom".to_string(),
+            page :    "https://links.ear7h.net/users/1/links.html".to_string(),
+            author :  "julio",
+            links,
+            updated : &updated,
+        })
+    }
+
+    #[test]
+    fn titles_and_summaries() {
+        let mut a = link(1, "https://ear7h.net/a");
+        a.title = Some("mine".to_string());
+        a.page_title = Some("theirs".to_string());
+        a.page_description = Some("about a".to_string());
+
+        let mut b = link(2, "https://ear7h.net/b");
+        b.page_title = Some("theirs".to_string());
+
+        let c = link(3, "https://ear7h.net/c?d=1&e=2");
+
+        assert_eq!(title(&a), "mine");
+        assert_eq!(title(&b), "theirs");
+        assert_eq!(title(&c), "https://ear7h.net/c?d=1&e=2");
+        assert_eq!(summary(&a), Some("about a"));
+        assert_eq!(summary(&b), None);
+    }
+
+    #[test]
+    fn atom_feed() {
+        let mut a = link(7, "https://ear7h.net/a?b=1&c=2");
+        a.description = Some("a & b".to_string());
+        a.tags = models::Tags(vec!["rust".to_string()]);
+
+        let out = render(Format::Atom, &[a]);
+
+        assert!(out.contains("<title>julio&#39;s &lt;links&gt;</title>"));
+        assert!(out.contains("<updated>2021-10-01T08:30:05Z</updated>"));
+        assert!(out.contains(concat!(
+            "<entry>\n",
+            "<title>https://ear7h.net/a?b=1&amp;c=2</title>\n",
+            "<link rel=\"alternate\" ",
+            "href=\"https://ear7h.net/a?b=1&amp;c=2\"/>\n",
+            "<id>tag:links.ear7h.net,2021:links/7</id>\n",
+            "<published>2021-09-30T12:00:00Z</published>\n",
+            "<updated>2021-09-30T12:00:00Z</updated>\n",
+            "<summary>a &amp; b</summary>\n",
+            "<category term=\"rust\"/>\n",
+            "</entry>\n",
+        )));
+        assert!(out.ends_with("</feed>\n"));
+    }
+
+    #[test]
+    fn rss_feed() {
+        let out = render(Format::Rss, &[link(7, "https://ear7h.net/a")]);
+
+        assert!(out.contains(
+            "<lastBuildDate>Fri, 01 Oct 2021 08:30:05 GMT</lastBuildDate>"
+        ));
+        assert!(out.contains(concat!(
+            "<item>\n",
+            "<title>https://ear7h.net/a</title>\n",
+            "<link>https://ear7h.net/a</link>\n",
+            "<guid isPermaLink=\"false\">",
+            "tag:links.ear7h.net,2021:links/7</guid>\n",
+            "<pubDate>Thu, 30 Sep 2021 12:00:00 GMT</pubDate>\n",
+            "</item>\n",
+        )));
+        assert!(out.ends_with("</channel>\n</rss>\n"));
+    }
+
+    #[test]
+    fn empty_feed() {
+        let out = render(Format::Atom, &[]);
+
+        assert!(!out.contains("<entry>"));
+        assert!(out.contains(concat!(
+            "<link rel=\"self\" ",
+            "href=\"https://links.ear7h.net/users/1/links.atom\"/>",
+        )));
+    }
+}
@@ -0,0 +1,38 @@
+use hmac::{Hmac, Mac, NewMac};
+use sha2::{Digest, Sha256};
+
+pub fn hex(bytes : &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn sha256(data : &[u8]) -> Vec<u8> {
+    Sha256::digest(data).to_vec()
+}
+
+pub fn sha256_hex(data : &[u8]) -> String {
+    hex(&sha256(data))
+}
+
+/// hmac-sha256, rfc 2104
+pub fn hmac_sha256(key : &[u8], data : &[u8]) -> Vec<u8> {
+    // any key length is allowed
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(data);
+
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hmac_sha256_rfc_4231() {
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+
+        assert_eq!(
+            hex(&mac),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        );
+    }
+}
@@ -0,0 +1,358 @@
+//! a forgiving html tokenizer, good enough for pulling things out of and
+//! rewriting archived pages. it doesn't build a tree or fix up bad markup.
+
+pub fn escape(s : &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// decodes the common named entities and numeric character references
+pub fn unescape(s : &str) -> String {
+    if !s.contains('&') {
+        return s.to_string()
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        // entities are short, don't go looking for a far away semicolon
+        let semicolon = rest.bytes().take(12).position(|b| b == b';');
+        let end = match semicolon {
+            Some(end) => end,
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+                continue
+            },
+        };
+
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => {
+                entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(str::parse::<u32>))
+                    .and_then(|n| n.ok())
+                    .and_then(std::char::from_u32)
+            },
+        };
+
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// elements whose content is text rather than markup
+const RAW_TEXT : &[&str] = &["script", "style", "textarea", "title"];
+
+/// elements which never have an end tag
+pub const VOID : &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta",
+    "source", "track", "wbr",
+];
+
+#[derive(Debug, Clone)]
+pub struct Tag {
+    /// lower case
+    pub name :         String,
+    /// lower case names with unescaped values
+    pub attrs :        Vec<(String, Option<String>)>,
+    pub self_closing : bool,
+}
+
+impl Tag {
+    pub fn attr(&self, name : &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    pub fn set_attr(&mut self, name : &str, value : String) {
+        match self.attrs.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = Some(value),
+            None => self.attrs.push((name.to_string(), Some(value))),
+        }
+    }
+
+    pub fn remove_attr(&mut self, name : &str) {
+        self.attrs.retain(|(n, _)| n != name);
+    }
+
+    /// whether the space separated attribute contains the word, e.g.
+    /// rel="shortcut icon" contains "icon"
+    pub fn attr_contains(&self, name : &str, word : &str) -> bool {
+        self.attr(name)
+            .map(|v| v.split_whitespace().any(|w| w.eq_ignore_ascii_case(word)))
+            .unwrap_or(false)
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut s = format!("<{}", self.name);
+
+        for (name, value) in self.attrs.iter() {
+            match value {
+                Some(value) => {
+                    s += &format!(" {}=\"{}\"", name, escape(value));
+                },
+                None => {
+                    s += &format!(" {}", name);
+                },
+            }
+        }
+
+        if self.self_closing {
+            s.push_str(" /");
+        }
+
+        s.push('>');
+        s
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Token<'a> {
+    /// text between tags, still escaped
+    Text(&'a str),
+    Start(Tag),
+    /// the lower case name of the element being closed
+    End(String),
+    /// comments, doctypes and anything else which should be passed through
+    Other(&'a str),
+}
+
+pub struct Tokenizer<'a> {
+    rest :     &'a str,
+    /// set after the start tag of a raw text element, its content is the next
+    /// token
+    raw_text : Option<String>,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(html : &'a str) -> Self {
+        Self {
+            rest :     html,
+            raw_text : None,
+        }
+    }
+
+    fn take(&mut self, n : usize) -> &'a str {
+        let (taken, rest) = self.rest.split_at(n);
+        self.rest = rest;
+        taken
+    }
+}
+
+pub fn find_ignore_case(haystack : &str, needle : &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+fn is_name_char(c : char) -> bool {
+    !c.is_whitespace() && !matches!(c, '/' | '>' | '=' | '"' | '\'')
+}
+
+/// parses the inside of a start tag, without the angle brackets
+fn parse_tag(inner : &str) -> Tag {
+    let self_closing = inner.ends_with('/');
+    let inner = inner.trim_end_matches('/');
+
+    let name_end = inner.find(|c| !is_name_char(c)).unwrap_or(inner.len());
+    let name = inner[..name_end].to_ascii_lowercase();
+
+    let mut attrs = Vec::new();
+    let mut rest = &inner[name_end..];
+
+    loop {
+        rest = rest
+            .trim_start_matches(|c : char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            break
+        }
+
+        let end = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+        if end == 0 {
+            // a stray quote or equals sign
+            rest = &rest[1..];
+            continue
+        }
+
+        let attr_name = rest[..end].to_ascii_lowercase();
+        rest = rest[end..].trim_start();
+
+        let value = if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+
+            let (value, remaining) = match after.chars().next() {
+                Some(q @ '"') | Some(q @ '\'') => {
+                    let end = after[1..].find(q).map(|i| i + 1)
+                        .unwrap_or(after.len());
+                    (&after[1..end], &after[(end + 1).min(after.len())..])
+                },
+                _ => {
+                    let end = after.find(char::is_whitespace)
+                        .unwrap_or(after.len());
+                    after.split_at(end)
+                },
+            };
+
+            rest = remaining;
+            Some(unescape(value))
+        } else {
+            None
+        };
+
+        attrs.push((attr_name, value));
+    }
+
+    Tag {
+        name,
+        attrs,
+        self_closing,
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        if self.rest.is_empty() {
+            return None
+        }
+
+        if let Some(name) = self.raw_text.take() {
+            let end = find_ignore_case(self.rest, &format!("</{}", name))
+                .unwrap_or(self.rest.len());
+
+            if end > 0 {
+                return Some(Token::Text(self.take(end)))
+            }
+        }
+
+        if !self.rest.starts_with('<') {
+            let end = self.rest.find('<').unwrap_or(self.rest.len());
+            return Some(Token::Text(self.take(end)))
+        }
+
+        if self.rest.starts_with("<!--") {
+            let end = self.rest.find("-->")
+                .map(|i| i + 3)
+                .unwrap_or(self.rest.len());
+            return Some(Token::Other(self.take(end)))
+        }
+
+        let next = self.rest[1..].chars().next();
+        let end = match self.rest.find('>') {
+            Some(end) => end + 1,
+            None => return Some(Token::Text(self.take(self.rest.len()))),
+        };
+
+        match next {
+            Some('/') => {
+                let inner = &self.take(end)[2..end - 1];
+                let name = inner
+                    .trim()
+                    .split(|c : char| c.is_whitespace())
+                    .next()
+                    .unwrap_or("")
+                    .to_ascii_lowercase();
+
+                Some(Token::End(name))
+            },
+            Some(c) if c.is_ascii_alphabetic() => {
+                let tag = parse_tag(&self.take(end)[1..end - 1]);
+
+                if RAW_TEXT.contains(&tag.name.as_str()) && !tag.self_closing {
+                    self.raw_text = Some(tag.name.clone());
+                }
+
+                Some(Token::Start(tag))
+            },
+            Some('!') | Some('?') => Some(Token::Other(self.take(end))),
+            _ => {
+                // a lone "<" in text
+                Some(Token::Text(self.take(1)))
+            },
+        }
+    }
+}
+
+/// elements which start a new line when the page is turned into text
+pub const BLOCK : &[&str] = &[
+    "address", "article", "aside", "blockquote", "br", "dd", "div", "dl",
+    "dt", "figcaption", "figure", "footer", "h1", "h2", "h3", "h4", "h5", "h6",
+    "header", "hr", "li", "main", "nav", "ol", "p", "pre", "section", "table",
+    "td", "th", "tr", "ul",
+];
+
+/// the readable text of the page, one line per block element with
+/// whitespace collapsed. scripts and styles are left out.
+pub fn text(html : &str) -> String {
+    let mut out = String::new();
+    let mut line = String::new();
+    let mut skip = false;
+
+    let mut end_line = |line : &mut String| {
+        let collapsed = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !collapsed.is_empty() {
+            out.push_str(&collapsed);
+            out.push('\n');
+        }
+        line.clear();
+    };
+
+    for token in Tokenizer::new(html) {
+        match token {
+            Token::Start(tag) => {
+                skip = tag.name == "script" || tag.name == "style";
+
+                if BLOCK.contains(&tag.name.as_str()) {
+                    end_line(&mut line);
+                }
+            },
+            Token::End(name) => {
+                skip = false;
+
+                if BLOCK.contains(&name.as_str()) {
+                    end_line(&mut line);
+                }
+            },
+            Token::Text(_) if skip => {},
+            Token::Text(text) => {
+                line.push_str(&unescape(text));
+            },
+            Token::Other(_) => {},
+        }
+    }
+
+    end_line(&mut line);
+    out
+}
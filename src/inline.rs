@@ -0,0 +1,384 @@
+//! rewrites archived pages into a single self-contained document. stylesheets
+//! are inlined into <style> elements, images and fonts become data uris and
+//! scripts are removed.
+//!
+//! fetching is left to the caller: html_assets and css_urls list what's
+//! needed, and the rewrite functions take what was fetched.
+
+use std::collections::HashMap;
+
+use url::Url;
+
+use crate::html::{Tag, Token, Tokenizer};
+
+/// the assets referenced by a page
+#[derive(Debug, Default)]
+pub struct Assets {
+    pub stylesheets : Vec<Url>,
+    /// images, fonts and anything else that can be a data uri
+    pub resources :   Vec<Url>,
+}
+
+/// attributes which hold a single resource url
+const RESOURCE_ATTRS : &[(&str, &str)] = &[
+    ("img", "src"),
+    ("input", "src"),
+    ("source", "src"),
+    ("video", "poster"),
+    ("audio", "src"),
+    ("video", "src"),
+    ("track", "src"),
+    ("body", "background"),
+    ("table", "background"),
+    ("td", "background"),
+];
+
+pub fn data_uri(content_type : &str, data : &[u8]) -> String {
+    format!("data:{};base64,{}", content_type, base64::encode(data))
+}
+
+fn resolve(base : &Url, reference : &str) -> Option<Url> {
+    let reference = reference.trim();
+    if reference.is_empty()
+        || reference.starts_with("data:")
+        || reference.starts_with('#')
+    {
+        return None
+    }
+
+    base.join(reference)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// calls f with the byte range of the url inside every url(...) in the css
+fn for_each_css_url(css : &str, mut f : impl FnMut(usize, usize)) {
+    let mut off = 0;
+
+    while let Some(i) = crate::html::find_ignore_case(&css[off..], "url(") {
+        let start = off + i + "url(".len();
+        let end = match css[start..].find(')') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        let inner = &css[start..end];
+        let trimmed = inner.trim().trim_matches(|c| c == '"' || c == '\'');
+        let inner_start = start + inner.find(trimmed).unwrap_or(0);
+
+        f(inner_start, inner_start + trimmed.len());
+        off = end + 1;
+    }
+}
+
+/// the urls referenced by a stylesheet, e.g. fonts and background images
+pub fn css_urls(css : &str, base : &Url) -> Vec<Url> {
+    let mut urls = Vec::new();
+
+    for_each_css_url(css, |start, end| {
+        urls.extend(resolve(base, &css[start..end]));
+    });
+
+    urls
+}
+
+/// replaces the urls in the stylesheet with their data uris, urls which
+/// weren't fetched are made absolute
+pub fn rewrite_css(
+    css : &str,
+    base : &Url,
+    data : &HashMap<String, String>,
+) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut last = 0;
+
+    for_each_css_url(css, |start, end| {
+        if let Some(url) = resolve(base, &css[start..end]) {
+            out.push_str(&css[last..start]);
+            match data.get(url.as_str()) {
+                Some(uri) => out.push_str(uri),
+                None => out.push_str(url.as_str()),
+            }
+            last = end;
+        }
+    });
+
+    out.push_str(&css[last..]);
+    out
+}
+
+fn is_stylesheet(tag : &Tag) -> bool {
+    tag.name == "link" && tag.attr_contains("rel", "stylesheet")
+}
+
+fn is_icon(tag : &Tag) -> bool {
+    tag.name == "link" && tag.attr_contains("rel", "icon")
+}
+
+/// everything the page needs fetched to be rendered offline
+pub fn html_assets(html : &str, base : &Url) -> Assets {
+    let mut assets = Assets::default();
+    let mut in_style = false;
+
+    for token in Tokenizer::new(html) {
+        match token {
+            Token::Start(tag) => {
+                in_style = tag.name == "style";
+
+                if is_stylesheet(&tag) {
+                    assets.stylesheets.extend(
+                        tag.attr("href").and_then(|href| resolve(base, href)),
+                    );
+                }
+
+                if is_icon(&tag) {
+                    assets.resources.extend(
+                        tag.attr("href").and_then(|href| resolve(base, href)),
+                    );
+                }
+
+                for (name, attr) in RESOURCE_ATTRS.iter() {
+                    if tag.name == *name {
+                        assets.resources.extend(
+                            tag.attr(attr).and_then(|src| resolve(base, src)),
+                        );
+                    }
+                }
+
+                if let Some(style) = tag.attr("style") {
+                    assets.resources.extend(css_urls(style, base));
+                }
+            },
+            Token::Text(css) if in_style => {
+                assets.resources.extend(css_urls(css, base));
+            },
+            _ => in_style = false,
+        }
+    }
+
+    assets.stylesheets.dedup();
+    assets.resources.sort();
+    assets.resources.dedup();
+
+    assets
+}
+
+/// rewrites the page into a self-contained document. stylesheets maps
+/// stylesheet urls to their already rewritten css, data maps resource urls
+/// to data uris.
+pub fn rewrite_html(
+    html : &str,
+    base : &Url,
+    stylesheets : &HashMap<String, String>,
+    data : &HashMap<String, String>,
+) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut in_style = false;
+    let mut in_script = false;
+
+    let absolute = |reference : &str| {
+        resolve(base, reference).map(|url| {
+            data.get(url.as_str())
+                .cloned()
+                .unwrap_or_else(|| url.to_string())
+        })
+    };
+
+    for token in Tokenizer::new(html) {
+        match token {
+            Token::Start(mut tag) => {
+                in_style = tag.name == "style";
+                in_script = tag.name == "script";
+
+                let refresh = tag.name == "meta"
+                    && tag.attr("http-equiv")
+                        .map(|v| v.eq_ignore_ascii_case("refresh"))
+                        .unwrap_or(false);
+
+                // scripts can't run in the sandbox anyway, and urls are made
+                // absolute so base would break them
+                if tag.name == "script" || tag.name == "base" || refresh {
+                    continue
+                }
+
+                if is_stylesheet(&tag) {
+                    let css = tag
+                        .attr("href")
+                        .and_then(|href| resolve(base, href))
+                        .and_then(|url| stylesheets.get(url.as_str()));
+
+                    if let Some(css) = css {
+                        out.push_str("<style>");
+                        // keep the stylesheet from closing the element early
+                        out.push_str(&css.replace("</style", "<\\/style"));
+                        out.push_str("</style>");
+                        continue
+                    }
+                }
+
+                // event handlers are scripts too
+                tag.attrs.retain(|(name, _)| !name.starts_with("on"));
+
+                let mut rewrites = Vec::new();
+                for (name, value) in tag.attrs.iter() {
+                    let value = match value {
+                        Some(value) => value,
+                        None => continue,
+                    };
+
+                    let rewritten = match name.as_str() {
+                        "style" => Some(rewrite_css(value, base, data)),
+                        "href" | "src" | "poster" | "background" => {
+                            absolute(value)
+                        },
+                        _ => None,
+                    };
+
+                    if let Some(rewritten) = rewritten {
+                        rewrites.push((name.clone(), rewritten));
+                    }
+                }
+
+                for (name, value) in rewrites {
+                    tag.set_attr(&name, value);
+                }
+
+                // the src was inlined, srcset would still go to the network
+                if tag.attr("src").map(|s| s.starts_with("data:")) == Some(true)
+                {
+                    tag.remove_attr("srcset");
+                }
+
+                out.push_str(&tag.to_html());
+            },
+            Token::Text(_) if in_script => {},
+            Token::Text(css) if in_style => {
+                out.push_str(&rewrite_css(css, base, data));
+            },
+            Token::End(name) => {
+                in_style = false;
+
+                if in_script && name == "script" {
+                    in_script = false;
+                    continue
+                }
+
+                out.push_str(&format!("</{}>", name));
+            },
+            Token::Text(text) | Token::Other(text) => {
+                out.push_str(text);
+            },
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Url {
+        Url::parse("https://ear7h.net/posts/a.html").unwrap()
+    }
+
+    fn map(pairs : &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn css_urls_resolved() {
+        let css = r#"a { background: url( "bg.png" ) }
+            @font-face { src: URL('/font.woff2') }
+            b { background: url(data:image/png;base64,AA==) }"#;
+
+        let urls = css_urls(css, &base())
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        assert_eq!(urls, vec![
+            "https://ear7h.net/posts/bg.png",
+            "https://ear7h.net/font.woff2",
+        ]);
+    }
+
+    #[test]
+    fn rewrite_css_inlines_fetched() {
+        let css =
+            "a { background: url('bg.png') } b { background: url(c.png) }";
+        let data = map(&[(
+            "https://ear7h.net/posts/bg.png",
+            "data:image/png;base64,AA==",
+        )]);
+
+        assert_eq!(
+            rewrite_css(css, &base(), &data),
+            "a { background: url('data:image/png;base64,AA==') } \
+             b { background: url(https://ear7h.net/posts/c.png) }",
+        );
+    }
+
+    #[test]
+    fn html_assets_listed() {
+        let html = r#"<html><head>
+            <link rel="stylesheet" href="/style.css">
+            <link rel="icon" href="favicon.ico">
+            <style>body { background: url(bg.png) }</style>
+            </head><body>
+            <img src="img.png"><img src="img.png"><img src="data:,x">
+            <div style="background: url('/div.png')"></div>
+            </body></html>"#;
+
+        let assets = html_assets(html, &base());
+        let strings = |urls : Vec<Url>| {
+            urls.into_iter().map(String::from).collect::<Vec<_>>()
+        };
+
+        assert_eq!(strings(assets.stylesheets), vec![
+            "https://ear7h.net/style.css",
+        ]);
+        assert_eq!(strings(assets.resources), vec![
+            "https://ear7h.net/div.png",
+            "https://ear7h.net/posts/bg.png",
+            "https://ear7h.net/posts/favicon.ico",
+            "https://ear7h.net/posts/img.png",
+        ]);
+    }
+
+    #[test]
+    fn rewrite_html_self_contained() {
+        let html = concat!(
+            r#"<html><head><base href="/other/">"#,
+            r#"<meta http-equiv="Refresh" content="0; url=/x">"#,
+            r#"<link rel="stylesheet" href="/style.css">"#,
+            r#"<script>alert("</p>")</script>"#,
+            r#"</head><body onload="f()">"#,
+            r#"<a href="b.html">b</a>"#,
+            r#"<img src="img.png" srcset="img-2x.png 2x">"#,
+            r#"<img src="other.png">"#,
+            r#"</body></html>"#,
+        );
+        let stylesheets =
+            map(&[("https://ear7h.net/style.css", "p {}</style>")]);
+        let data = map(&[(
+            "https://ear7h.net/posts/img.png",
+            "data:image/png;base64,AA==",
+        )]);
+
+        assert_eq!(
+            rewrite_html(html, &base(), &stylesheets, &data),
+            concat!(
+                r#"<html><head>"#,
+                r#"<style>p {}<\/style></style>"#,
+                r#"</head><body>"#,
+                r#"<a href="https://ear7h.net/posts/b.html">b</a>"#,
+                r#"<img src="data:image/png;base64,AA==">"#,
+                r#"<img src="https://ear7h.net/posts/other.png">"#,
+                r#"</body></html>"#,
+            ),
+        );
+    }
+}
@@ -1,8 +1,30 @@
 mod error;
 pub use error::*;
 
+// first, so the log! macro can be used in the modules after it
+#[macro_use]
+pub mod logging;
+
+pub mod admin;
 pub mod api;
+pub(crate) mod api_tokens;
+pub mod archiver;
+pub mod bookmarks;
+pub mod canonical;
+pub mod checker;
+pub mod csrf;
 pub mod database;
+pub mod diff;
+pub mod export;
+pub mod feed;
+pub(crate) mod hashing;
+pub mod html;
+pub mod inline;
+pub mod metadata;
 pub mod models;
+pub mod netguard;
+pub mod ratelimit;
+pub mod reader;
 pub(crate) mod time_utils;
 pub mod ui;
+pub mod warc;
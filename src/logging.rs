@@ -0,0 +1,194 @@
+//! structured logs, one line per event in logfmt or json. events logged
+//! while handling a request carry its id, and the user's id once they're
+//! authenticated, so everything a request did can be found together.
+
+use std::cell::Cell;
+use std::future::Future;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use rand::RngCore;
+use serde::Deserialize;
+
+use crate::hashing;
+
+/// the format of the timestamps, rfc 3339 in utc with milliseconds
+const TIME_FORMAT : &[time::format_description::FormatItem<'static>] =
+    time::macros::format_description!(
+        "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:3]Z"
+    );
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Logfmt,
+    Json,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// events less severe than this are dropped
+    pub level :  Level,
+    pub format : Format,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            level :  Level::Info,
+            format : Format::Logfmt,
+        }
+    }
+}
+
+static LEVEL : AtomicU8 = AtomicU8::new(Level::Info as u8);
+static JSON : AtomicBool = AtomicBool::new(false);
+
+/// sets the level and format, logging works with the defaults before this
+pub fn init(config : &Config) {
+    LEVEL.store(config.level as u8, Ordering::Relaxed);
+    JSON.store(matches!(config.format, Format::Json), Ordering::Relaxed);
+}
+
+pub fn enabled(level : Level) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// what's known about the request being handled
+struct Context {
+    id :      String,
+    user_id : Cell<Option<u32>>,
+}
+
+tokio::task_local! {
+    static REQUEST : Context;
+}
+
+/// runs the request's handler, events it logs get the request's id
+pub async fn scope<F : Future>(request_id : String, f : F) -> F::Output {
+    let ctx = Context {
+        id :      request_id,
+        user_id : Cell::new(None),
+    };
+
+    REQUEST.scope(ctx, f).await
+}
+
+/// records who made the request, for the events logged after
+pub fn set_user(user_id : u32) {
+    let _ = REQUEST.try_with(|ctx| ctx.user_id.set(Some(user_id)));
+}
+
+pub fn new_request_id() -> String {
+    let mut bytes = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    hashing::hex(&bytes)
+}
+
+/// ids from clients and proxies are used if they're reasonable, so requests
+/// can be followed across services
+pub fn valid_request_id(id : &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+/// logfmt values are quoted if they'd be ambiguous otherwise
+fn logfmt_value(value : &str) -> String {
+    let plain = !value.is_empty()
+        && !value.contains(|c : char| {
+            c.is_whitespace() || c.is_control() || c == '"' || c == '='
+        });
+
+    if plain {
+        return value.to_string()
+    }
+
+    format!("{:?}", value)
+}
+
+/// writes the event, use the log! macro instead
+pub fn write(level : Level, msg : &str, fields : &[(&str, String)]) {
+    if !enabled(level) {
+        return
+    }
+
+    let ts = time::OffsetDateTime::now_utc().format(&TIME_FORMAT).unwrap();
+
+    let mut all = vec![
+        ("ts", ts),
+        ("level", level.as_str().to_string()),
+        ("msg", msg.to_string()),
+    ];
+
+    let _ = REQUEST.try_with(|ctx| {
+        all.push(("request_id", ctx.id.clone()));
+        if let Some(user_id) = ctx.user_id.get() {
+            all.push(("user_id", user_id.to_string()));
+        }
+    });
+
+    all.extend(fields.iter().cloned());
+
+    let line = if JSON.load(Ordering::Relaxed) {
+        let pairs = all
+            .iter()
+            .map(|(key, value)| {
+                format!(
+                    "{}:{}",
+                    serde_json::to_string(key).unwrap(),
+                    serde_json::to_string(value).unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        format!("{{{}}}", pairs.join(","))
+    } else {
+        all.iter()
+            .map(|(key, value)| format!("{}={}", key, logfmt_value(value)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    // one write per line, so lines from different threads don't mix
+    let stdout = std::io::stdout();
+    let _ = writeln!(stdout.lock(), "{}", line);
+}
+
+/// logs an event with key = value fields, the values are displayed:
+///
+/// log!(Info, "request", status = 200, ms = 1.5)
+macro_rules! log {
+    ($level:ident, $msg:expr $(, $key:ident = $value:expr)* $(,)?) => {
+        if $crate::logging::enabled($crate::logging::Level::$level) {
+            $crate::logging::write(
+                $crate::logging::Level::$level,
+                $msg,
+                &[$((stringify!($key), format!("{}", $value))),*],
+            )
+        }
+    };
+}
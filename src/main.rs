@@ -1,10 +1,8 @@
 
-use link_archive::api;
+use link_archive::{api, logging, Error};
 
 #[tokio::main]
 async fn main() {
-    println!("starting server");
-
     let args = std::env::args().collect::<Vec<_>>();
     let config = match &args[..] {
         [_, config] => config,
@@ -14,7 +12,27 @@ async fn main() {
         }
     };
 
-    let (server, addr) = api::new_server(&config).unwrap();
+    let (server, addr) = match api::new_server(config) {
+        Ok(x) => x,
+        Err(Error::UnknownMigration(name)) => {
+            eprintln!(
+                "the database has migration {} which this version does not \
+                know about, was it opened by a newer version?",
+                name,
+            );
+            std::process::exit(1);
+        },
+        Err(err) => {
+            eprintln!("could not start the server: {:?}", err);
+            std::process::exit(1);
+        },
+    };
+    logging::write(
+        logging::Level::Info,
+        "starting server",
+        &[("addr", addr.to_string())],
+    );
+
     http_mux::hyper::serve_addr(api::routes(server), &addr).await.unwrap();
 }
 
@@ -0,0 +1,292 @@
+//! metadata describing a page, pulled out of its html. this covers the
+//! <title>, OpenGraph and Twitter card tags, and the usual <meta> and <link>
+//! tags for the rest.
+
+use serde::Serialize;
+use url::Url;
+
+use crate::html::{unescape, Token, Tokenizer};
+
+/// longer values are cut, some pages stuff whole articles into descriptions
+const MAX_LEN : usize = 1000;
+
+#[derive(Debug, Default, Serialize)]
+pub struct Metadata {
+    pub title :         Option<String>,
+    pub description :   Option<String>,
+    /// preview image url
+    pub image :         Option<String>,
+    pub site_name :     Option<String>,
+    pub canonical_url : Option<String>,
+    pub author :        Option<String>,
+    /// as written on the page, usually but not always iso 8601
+    pub published :     Option<String>,
+    pub favicon :       Option<String>,
+}
+
+/// collapses whitespace, empty values are none
+fn clean(s : &str) -> Option<String> {
+    let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+    if s.is_empty() {
+        return None
+    }
+
+    match s.char_indices().nth(MAX_LEN) {
+        Some((i, _)) => Some(s[..i].to_string()),
+        None => Some(s),
+    }
+}
+
+/// the first value of each tag wins, like browsers and scrapers do
+fn set(slot : &mut Option<String>, value : Option<String>) {
+    if slot.is_none() {
+        *slot = value;
+    }
+}
+
+fn resolve(base : &Url, reference : &str) -> Option<String> {
+    base.join(reference.trim())
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .map(String::from)
+}
+
+/// the first of the candidates which was found, in order of preference
+fn first(candidates : &[&Option<String>]) -> Option<String> {
+    candidates.iter().find_map(|c| (*c).clone())
+}
+
+/// the values of the tags we look for, by the tag they came from
+#[derive(Default)]
+struct Found {
+    title :               Option<String>,
+    og_title :            Option<String>,
+    twitter_title :       Option<String>,
+    description :         Option<String>,
+    og_description :      Option<String>,
+    twitter_description : Option<String>,
+    og_image :            Option<String>,
+    twitter_image :       Option<String>,
+    og_site_name :        Option<String>,
+    application_name :    Option<String>,
+    canonical :           Option<String>,
+    og_url :              Option<String>,
+    author :              Option<String>,
+    article_author :      Option<String>,
+    twitter_creator :     Option<String>,
+    published_time :      Option<String>,
+    date :                Option<String>,
+    icon :                Option<String>,
+    apple_touch_icon :    Option<String>,
+}
+
+/// extracts the page's metadata, base is the url the page was fetched from
+/// and is used to resolve relative urls
+pub fn extract(html : &[u8], base : &Url) -> Metadata {
+    let html = String::from_utf8_lossy(html);
+    let mut found = Found::default();
+    let mut in_title = false;
+
+    for token in Tokenizer::new(&html) {
+        let tag = match token {
+            Token::Start(tag) => tag,
+            Token::Text(text) if in_title => {
+                if found.title.is_none() {
+                    found.title = clean(&unescape(text));
+                }
+                continue
+            },
+            Token::End(name) if name == "head" => break,
+            _ => {
+                in_title = false;
+                continue
+            },
+        };
+
+        in_title = tag.name == "title";
+
+        match tag.name.as_str() {
+            "meta" => {
+                let key = tag
+                    .attr("property")
+                    .or_else(|| tag.attr("name"))
+                    .or_else(|| tag.attr("itemprop"))
+                    .map(str::to_ascii_lowercase);
+                let content = tag.attr("content");
+
+                let (key, content) = match (key, content) {
+                    (Some(key), Some(content)) => (key, content),
+                    _ => continue,
+                };
+
+                let url = || resolve(base, content);
+                let text = || clean(content);
+
+                match key.as_str() {
+                    "og:title" => set(&mut found.og_title, text()),
+                    "twitter:title" => set(&mut found.twitter_title, text()),
+                    "description" => set(&mut found.description, text()),
+                    "og:description" => {
+                        set(&mut found.og_description, text())
+                    },
+                    "twitter:description" => {
+                        set(&mut found.twitter_description, text())
+                    },
+                    "og:image" | "og:image:url" | "og:image:secure_url" => {
+                        set(&mut found.og_image, url())
+                    },
+                    "twitter:image" | "twitter:image:src" => {
+                        set(&mut found.twitter_image, url())
+                    },
+                    "og:site_name" => set(&mut found.og_site_name, text()),
+                    "application-name" => {
+                        set(&mut found.application_name, text())
+                    },
+                    "og:url" => set(&mut found.og_url, url()),
+                    "author" => set(&mut found.author, text()),
+                    "article:author" => set(&mut found.article_author, text()),
+                    "twitter:creator" => {
+                        set(&mut found.twitter_creator, text())
+                    },
+                    "article:published_time" | "datepublished" => {
+                        set(&mut found.published_time, text())
+                    },
+                    "date" | "pubdate" | "dc.date" | "dcterms.date" => {
+                        set(&mut found.date, text())
+                    },
+                    _ => {},
+                }
+            },
+            "link" => {
+                let href = match tag.attr("href") {
+                    Some(href) => href,
+                    None => continue,
+                };
+
+                if tag.attr_contains("rel", "canonical") {
+                    set(&mut found.canonical, resolve(base, href));
+                }
+
+                if tag.attr_contains("rel", "icon") {
+                    set(&mut found.icon, resolve(base, href));
+                }
+
+                if tag.attr_contains("rel", "apple-touch-icon") {
+                    set(&mut found.apple_touch_icon, resolve(base, href));
+                }
+            },
+            // the body has started without the head being closed
+            "body" => break,
+            _ => {},
+        }
+    }
+
+    let f = &found;
+
+    Metadata {
+        title :         first(&[&f.og_title, &f.twitter_title, &f.title]),
+        description :   first(&[
+            &f.og_description,
+            &f.twitter_description,
+            &f.description,
+        ]),
+        image :         first(&[&f.og_image, &f.twitter_image]),
+        site_name :     first(&[&f.og_site_name, &f.application_name]),
+        canonical_url : first(&[&f.canonical, &f.og_url]),
+        author :        first(&[
+            &f.author,
+            &f.article_author,
+            &f.twitter_creator,
+        ]),
+        published :     first(&[&f.published_time, &f.date]),
+        favicon :       first(&[&f.icon, &f.apple_touch_icon])
+            .or_else(|| resolve(base, "/favicon.ico")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Url {
+        Url::parse("https://ear7h.net/posts/a.html").unwrap()
+    }
+
+    #[test]
+    fn title_precedence() {
+        let og = r#"<title>title</title>
+            <meta name="twitter:title" content="twitter">
+            <meta property="og:title" content="og">"#;
+        let twitter = r#"<title>title</title>
+            <meta name="twitter:title" content="twitter">"#;
+        let title = "<title>title</title>";
+
+        assert_eq!(
+            extract(og.as_bytes(), &base()).title.as_deref(),
+            Some("og"),
+        );
+        assert_eq!(
+            extract(twitter.as_bytes(), &base()).title.as_deref(),
+            Some("twitter"),
+        );
+        assert_eq!(
+            extract(title.as_bytes(), &base()).title.as_deref(),
+            Some("title"),
+        );
+    }
+
+    #[test]
+    fn relative_urls_resolved() {
+        let html = r#"<head>
+            <meta property="og:image" content="img/preview.png">
+            <link rel="shortcut icon" href="/icon.png">
+            <link rel="canonical" href="//ear7h.net/a">
+            </head>"#;
+
+        let meta = extract(html.as_bytes(), &base());
+        assert_eq!(
+            meta.image.as_deref(),
+            Some("https://ear7h.net/posts/img/preview.png"),
+        );
+        assert_eq!(meta.favicon.as_deref(), Some("https://ear7h.net/icon.png"));
+        assert_eq!(meta.canonical_url.as_deref(), Some("https://ear7h.net/a"));
+    }
+
+    #[test]
+    fn entities_decoded() {
+        let html = r#"<title>Tom &amp; Jerry&#39;s</title>
+            <meta name="description" content="a &lt;b&gt; &quot;c&quot;">"#;
+
+        let meta = extract(html.as_bytes(), &base());
+        assert_eq!(meta.title.as_deref(), Some("Tom & Jerry's"));
+        assert_eq!(meta.description.as_deref(), Some("a <b> \"c\""));
+    }
+
+    #[test]
+    fn missing_and_empty_tags() {
+        let html = r#"<html><head>
+            <title>  </title>
+            <meta property="og:title" content="">
+            <meta name="description">
+            <meta name="author" content="  julio
+                ear7h ">
+            </head><body>
+            <meta name="description" content="too late">
+            </body></html>"#;
+
+        let meta = extract(html.as_bytes(), &base());
+        assert_eq!(meta.title, None);
+        assert_eq!(meta.description, None);
+        assert_eq!(meta.image, None);
+        assert_eq!(meta.author.as_deref(), Some("julio ear7h"));
+        // browsers fall back to /favicon.ico
+        assert_eq!(
+            meta.favicon.as_deref(),
+            Some("https://ear7h.net/favicon.ico"),
+        );
+
+        let meta = extract(b"", &base());
+        assert_eq!(meta.title, None);
+        assert_eq!(meta.published, None);
+    }
+}
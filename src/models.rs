@@ -1,4 +1,4 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 pub type Time = crate::time_utils::Time;
 
@@ -7,13 +7,209 @@ pub struct User {
     pub id :            u32,
     pub name :          String,
     pub created :       Time,
+    /// disabled by an admin
     pub deleted :       Option<Time>,
+    pub last_login :    Option<Time>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct Link {
-    pub user_id : u32,
-    pub url :     String,
+    pub id :                u32,
+    pub user_id :           u32,
+    pub url :               String,
+    /// what duplicates are found by, none if an older link has the same one
+    pub canonical :         Option<String>,
+    pub title :             Option<String>,
+    pub description :       Option<String>,
+    pub notes :             Option<String>,
+    pub created :           Time,
+    pub deleted :           Option<Time>,
+    pub tags :              Tags,
+    /// the number of successful snapshots of the link
+    pub snapshots :         u32,
+    /// when the latest successful snapshot was fetched
+    pub last_archived :     Option<Time>,
+    /// set while the link is waiting to be archived again
+    pub archive_requested : Option<Time>,
+    /// none until the link has been checked
+    pub health :            Option<Health>,
+    /// where the link redirects to, if it moved
+    pub moved_to :          Option<String>,
+    /// when the link was last checked
+    pub checked :           Option<Time>,
+    // extracted from the page, see metadata::Metadata
+    pub page_title :        Option<String>,
+    pub page_description :  Option<String>,
+    pub image :             Option<String>,
+    pub site_name :         Option<String>,
+    pub canonical_url :     Option<String>,
+    pub author :            Option<String>,
+    pub published :         Option<String>,
+    pub favicon :           Option<String>,
+    /// the length of the latest readable text
+    pub words :             Option<u32>,
+}
+
+/// how long it takes to read the words, in minutes
+pub fn reading_minutes(words : u32) -> u32 {
+    const WORDS_PER_MINUTE : u32 = 230;
+
+    let minutes = f64::from(words) / f64::from(WORDS_PER_MINUTE);
+    (minutes.ceil() as u32).max(1)
+}
+
+#[derive(Debug, Serialize)]
+pub struct Snapshot {
+    pub id :             u32,
+    pub link_id :        u32,
+    pub url :            String,
+    pub fetched :        Time,
+    pub status :         Option<u16>,
+    /// json array of [name, value] pairs
+    pub headers :        String,
+    pub content_type :   Option<String>,
+    pub digest :         Option<String>,
+    /// the self-contained version of html pages
+    pub inlined_digest : Option<String>,
+    /// the reader mode version of html pages
+    pub reader_digest :  Option<String>,
+    /// the readable text of html pages
+    pub text_digest :    Option<String>,
+    pub words :          Option<u32>,
+    pub error :          Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiToken {
+    pub id :        u32,
+    pub user_id :   u32,
+    pub name :      String,
+    pub created :   Time,
+    pub last_used : Option<Time>,
+    pub revoked :   Option<Time>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    #[default]
+    Newest,
+    Oldest,
+    /// alphabetical by domain
+    Domain,
+}
+
+/// the outcome of the latest link check
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Health {
+    Ok,
+    /// an error status or no response at all
+    Broken,
+    /// redirects somewhere else
+    Moved,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LinksPage {
+    pub links :  Vec<Link>,
+    pub sort :   Sort,
+    /// only links with this health are listed
+    pub health : Option<Health>,
+    /// the cursor for the next page, if there is one
+    pub next :   Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    pub link :    Link,
+    /// the matched text, highlights are delimited by \u{2} and \u{3}
+    pub snippet : String,
+}
+
+/// the names of the tags on a link
+#[derive(Debug, Serialize)]
+pub struct Tags(pub Vec<String>);
+
+#[derive(Debug, Serialize)]
+pub struct Tag {
+    pub name :  String,
+    pub links : u32,
+}
+
+/// who can see a collection
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    /// listed on the user's public page
+    Public,
+    /// anyone with the link to it
+    Unlisted,
+    Private,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Collection {
+    pub id :         u32,
+    pub user_id :    u32,
+    pub name :       String,
+    pub visibility : Visibility,
+    /// the collection's url for people other than the owner
+    pub slug :       String,
+    pub created :    Time,
+    pub links :      u32,
+}
+
+/// what a member can do in a workspace, in increasing order
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// reads the links
+    Viewer,
+    /// adds, edits and deletes links
+    Editor,
+    /// manages the members, and api tokens for the workspace
+    Owner,
+}
+
+/// a workspace the user is a member of. workspaces are accounts like users,
+/// the links in them are owned by the workspace's id.
+#[derive(Debug, Serialize)]
+pub struct Workspace {
+    pub id :      u32,
+    pub name :    String,
     pub created : Time,
-    pub deleted : Option<Time>,
+    /// the user's role in it
+    pub role :    Role,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Member {
+    pub user_id : u32,
+    pub name :    String,
+    pub role :    Role,
+    pub added :   Time,
+}
+
+/// an account as listed for admins
+#[derive(Debug, Serialize)]
+pub struct UserSummary {
+    pub id :         u32,
+    pub name :       String,
+    pub created :    Time,
+    pub deleted :    Option<Time>,
+    pub last_login : Option<Time>,
+    pub workspace :  bool,
+    /// links which aren't in the trash
+    pub links :      u32,
+}
+
+/// tag names are restricted so they can be used as-is in urls
+pub fn valid_tag(name : &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
 }
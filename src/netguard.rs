@@ -0,0 +1,262 @@
+//! keeps the fetches of saved urls off of the server's own network, so
+//! saving a link can't be used to reach services which aren't public
+
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use hyper::client::connect::dns::Name;
+use hyper::client::HttpConnector;
+use hyper::service::Service;
+use hyper_rustls::HttpsConnector;
+use serde::Deserialize;
+
+use crate::{Error, Result};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    /// addresses which may be fetched even though they aren't public, e.g.
+    /// 127.0.0.1 while testing
+    #[serde(default)]
+    pub allow : Vec<IpAddr>,
+}
+
+fn is_public_v4(ip : Ipv4Addr) -> bool {
+    let [a, b, _, _] = ip.octets();
+    // "this network" is 0.0.0.0/8, carrier grade nat 100.64.0.0/10
+    let this_network = a == 0;
+    let shared = a == 100 && b & 0xc0 == 64;
+
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || this_network
+        || shared)
+}
+
+fn is_public_v6(ip : Ipv6Addr) -> bool {
+    let segment = ip.segments()[0];
+    // unique local addresses are fc00::/7, link local ones fe80::/10
+    let unique_local = segment & 0xfe00 == 0xfc00;
+    let link_local = segment & 0xffc0 == 0xfe80;
+
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || unique_local
+        || link_local)
+}
+
+/// the ipv4 address reached through an ipv6 one, if any: ::ffff:a.b.c.d
+/// is a.b.c.d, and so is 64:ff9b::a.b.c.d through nat64
+fn embedded_v4(ip : Ipv6Addr) -> Option<Ipv4Addr> {
+    let segments = ip.segments();
+    let nat64 = segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0];
+
+    match ip.to_ipv4_mapped() {
+        Some(v4) => Some(v4),
+        None if nat64 => Some(Ipv4Addr::from(
+            (u32::from(segments[6]) << 16) | u32::from(segments[7]),
+        )),
+        None => None,
+    }
+}
+
+/// the address as it's checked, ipv6 addresses which reach an ipv4 one are
+/// that ipv4 address
+fn canonical(ip : IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => embedded_v4(v6).map_or(ip, IpAddr::V4),
+        ip => ip,
+    }
+}
+
+/// whether the address is reachable from the internet at large, rather than
+/// only from the server's own network
+pub fn is_public(ip : IpAddr) -> bool {
+    match canonical(ip) {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => is_public_v6(ip),
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Guard {
+    allow : Arc<Vec<IpAddr>>,
+}
+
+impl Guard {
+    pub fn new(config : Config) -> Self {
+        Self {
+            allow : Arc::new(
+                config.allow.into_iter().map(canonical).collect(),
+            ),
+        }
+    }
+
+    pub fn allows(&self, ip : IpAddr) -> bool {
+        is_public(ip) || self.allow.contains(&canonical(ip))
+    }
+
+    /// refuses urls whose host is an address that isn't allowed. host names
+    /// are checked by the resolver of the connector, every time they're
+    /// resolved, so this is needed before every request.
+    pub fn check_url(&self, url : &url::Url) -> Result<()> {
+        let ip = match url.host() {
+            Some(url::Host::Ipv4(ip)) => IpAddr::V4(ip),
+            Some(url::Host::Ipv6(ip)) => IpAddr::V6(ip),
+            Some(url::Host::Domain(_)) => return Ok(()),
+            None => return Err(Error::InvalidUrl(url.to_string())),
+        };
+
+        if self.allows(ip) {
+            Ok(())
+        } else {
+            Err(Error::FetchFailed(format!("address not allowed: {}", url)))
+        }
+    }
+
+    /// an https connector which only connects to allowed addresses
+    pub fn connector(&self) -> HttpsConnector<HttpConnector<Resolver>> {
+        let mut http = HttpConnector::new_with_resolver(Resolver(self.clone()));
+        http.enforce_http(false);
+
+        let mut tls = rustls::ClientConfig::new();
+        tls.root_store = match rustls_native_certs::load_native_certs() {
+            Ok(store) | Err((Some(store), _)) => store,
+            Err((None, err)) => {
+                panic!("cannot access native cert store: {}", err)
+            },
+        };
+        // the client only speaks http/1.1
+        tls.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        (http, tls).into()
+    }
+}
+
+/// resolves host names, leaving out the addresses which aren't allowed
+#[derive(Debug, Clone)]
+pub struct Resolver(Guard);
+
+impl Service<Name> for Resolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = Pin<Box<
+        dyn Future<Output = io::Result<Self::Response>> + Send
+    >>;
+
+    fn poll_ready(&mut self, _ : &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name : Name) -> Self::Future {
+        let guard = self.0.clone();
+
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| guard.allows(addr.ip()))
+                .collect::<Vec<_>>();
+
+            if addrs.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("no allowed addresses for {}", name),
+                ))
+            }
+
+            Ok(addrs.into_iter())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s : &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn public_addresses() {
+        assert!(is_public(ip("93.184.216.34")));
+        assert!(is_public(ip("2606:2800:220:1:248:1893:25c8:1946")));
+        assert!(is_public(ip("::ffff:93.184.216.34")));
+        assert!(is_public(ip("64:ff9b::93.184.216.34")));
+        // next to the carrier grade nat range
+        assert!(is_public(ip("100.63.255.255")));
+        assert!(is_public(ip("100.128.0.1")));
+    }
+
+    #[test]
+    fn private_addresses() {
+        for s in &[
+            "127.0.0.1",
+            "0.1.2.3",
+            "100.64.0.1",
+            "100.127.255.254",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+            "::ffff:100.64.0.1",
+            "64:ff9b::127.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!is_public(ip(s)), "{}", s);
+        }
+    }
+
+    #[test]
+    fn check_url_literals() {
+        let guard = Guard::default();
+        let url = |s : &str| url::Url::parse(s).unwrap();
+
+        assert!(guard.check_url(&url("http://93.184.216.34/")).is_ok());
+        assert!(guard.check_url(&url("http://example.com/")).is_ok());
+        assert!(guard.check_url(&url("http://127.0.0.1:3000/")).is_err());
+        assert!(guard.check_url(&url("http://[::1]/")).is_err());
+        // the url crate normalizes other ipv4 notations
+        assert!(guard.check_url(&url("http://0x7f000001/")).is_err());
+
+        let guard = Guard::new(Config {
+            allow : vec![ip("127.0.0.1")],
+        });
+        assert!(guard.check_url(&url("http://127.0.0.1:3000/")).is_ok());
+        assert!(guard.check_url(&url("http://[::ffff:127.0.0.1]/")).is_ok());
+        assert!(guard.check_url(&url("http://[::1]/")).is_err());
+
+        assert!(guard.check_url(&url("http://[::ffff:10.0.0.1]/")).is_err());
+        assert!(guard.check_url(&url("http://100.64.0.1/")).is_err());
+    }
+
+    #[tokio::test]
+    async fn resolver_filters_names() {
+        let mut resolver = Resolver(Guard::default());
+        let name : Name = "localhost".parse().unwrap();
+        assert!(resolver.call(name).await.is_err());
+
+        let mut resolver = Resolver(Guard::new(Config {
+            allow : vec![ip("127.0.0.1")],
+        }));
+        let name : Name = "localhost".parse().unwrap();
+        let addrs = resolver.call(name).await.unwrap().collect::<Vec<_>>();
+        assert!(addrs.iter().all(|addr| addr.ip() == ip("127.0.0.1")));
+        assert!(!addrs.is_empty());
+    }
+}
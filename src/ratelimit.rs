@@ -0,0 +1,165 @@
+//! throttles logins so passwords can't be guessed by brute force. failed
+//! logins are counted by client address, and after a few of them the
+//! address is locked out for a time which doubles with each further
+//! failure. they aren't counted by username, or anyone could lock anyone
+//! else out.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// failed logins allowed before the first lockout
+    pub max_failures :     u32,
+    /// the first lockout, each failure after it doubles the time
+    pub lockout_secs :     u64,
+    /// the longest lockout
+    pub max_lockout_secs : u64,
+    /// failures are forgotten after this long without another one
+    pub reset_secs :       u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_failures :     5,
+            lockout_secs :     60,
+            max_lockout_secs : 60 * 60,
+            reset_secs :       24 * 60 * 60,
+        }
+    }
+}
+
+struct Failures {
+    count :        u32,
+    last :         Instant,
+    locked_until : Option<Instant>,
+}
+
+pub struct Limiter {
+    config : Config,
+    keys :   Mutex<HashMap<String, Failures>>,
+}
+
+/// the key for a client address
+pub fn ip_key(ip : &str) -> String {
+    format!("ip:{}", ip)
+}
+
+impl Limiter {
+    pub fn new(config : Config) -> Self {
+        Self {
+            config,
+            keys : Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn reset(&self) -> Duration {
+        Duration::from_secs(self.config.reset_secs)
+    }
+
+    /// how long until the key isn't locked out, if it is
+    pub fn locked(&self, key : &str) -> Option<Duration> {
+        let now = Instant::now();
+        let map = self.keys.lock().unwrap();
+
+        map.get(key)?
+            .locked_until
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+    }
+
+    /// counts a failed login. the key keeps its failures after a successful
+    /// one, or logging in to an account of one's own would reset them.
+    pub fn failure(&self, key : &str) {
+        let now = Instant::now();
+        let reset = self.reset();
+        let mut map = self.keys.lock().unwrap();
+
+        // forgotten keys are dropped here, so the map doesn't grow forever
+        map.retain(|_, failures| now - failures.last < reset);
+
+        let failures = map.entry(key.to_string()).or_insert(Failures {
+            count :        0,
+            last :         now,
+            locked_until : None,
+        });
+
+        failures.count += 1;
+        failures.last = now;
+
+        if failures.count >= self.config.max_failures {
+            let doublings = (failures.count - self.config.max_failures)
+                .min(32);
+            let secs = self.config.lockout_secs
+                .saturating_mul(1 << doublings)
+                .min(self.config.max_lockout_secs);
+
+            failures.locked_until = Some(now + Duration::from_secs(secs));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(reset_secs : u64) -> Limiter {
+        Limiter::new(Config {
+            max_failures :     3,
+            lockout_secs :     60,
+            max_lockout_secs : 200,
+            reset_secs,
+        })
+    }
+
+    fn secs(lock : Option<Duration>) -> Option<u64> {
+        // rounds away the time passed since locking
+        lock.map(|d| d.as_secs_f64().round() as u64)
+    }
+
+    #[test]
+    fn locks_out_after_max_failures() {
+        let limiter = limiter(3600);
+        let key = ip_key("10.0.0.1");
+
+        limiter.failure(&key);
+        limiter.failure(&key);
+        assert_eq!(limiter.locked(&key), None);
+
+        limiter.failure(&key);
+        assert_eq!(secs(limiter.locked(&key)), Some(60));
+        assert_eq!(limiter.locked(&ip_key("10.0.0.2")), None);
+    }
+
+    #[test]
+    fn lockout_doubles_up_to_max() {
+        let limiter = limiter(3600);
+        let key = ip_key("10.0.0.1");
+
+        for _ in 0..4 {
+            limiter.failure(&key);
+        }
+        assert_eq!(secs(limiter.locked(&key)), Some(120));
+
+        for _ in 0..40 {
+            limiter.failure(&key);
+        }
+        assert_eq!(secs(limiter.locked(&key)), Some(200));
+    }
+
+    #[test]
+    fn failures_are_forgotten_after_reset() {
+        let limiter = limiter(0);
+        let key = ip_key("10.0.0.1");
+
+        for _ in 0..5 {
+            limiter.failure(&key);
+        }
+        assert_eq!(limiter.locked(&key), None);
+    }
+}
@@ -0,0 +1,508 @@
+//! reader mode: finds the article in an archived page and strips everything
+//! around it, like navigation, ads and comments. the approach is a pared down
+//! version of the readability heuristics: paragraphs score their parents, and
+//! the best scoring element with few links is taken as the article.
+
+use url::Url;
+
+use crate::html::{self, escape, unescape, Token, Tokenizer, VOID};
+use crate::metadata;
+
+/// deeper elements are flattened into their ancestor, which keeps the
+/// recursion below bounded
+const MAX_DEPTH : usize = 256;
+
+/// elements which are never part of the article
+const REMOVE : &[&str] = &[
+    "aside", "button", "canvas", "dialog", "footer", "form", "header",
+    "iframe", "input", "label", "nav", "noscript", "object", "script",
+    "select", "style", "svg", "template", "textarea", "title",
+];
+
+/// class and id words of boilerplate
+const UNLIKELY : &[&str] = &[
+    "ad", "ads", "advert", "banner", "breadcrumb", "comment", "comments",
+    "cookie", "disqus", "footer", "menu", "modal", "nav", "newsletter",
+    "popup", "promo", "related", "share", "sharing", "sidebar", "social",
+    "sponsor", "subscribe",
+];
+
+/// class and id words which suggest the article, these win over UNLIKELY
+const LIKELY : &[&str] = &[
+    "article", "body", "content", "entry", "main", "post", "story", "text",
+];
+
+/// elements kept in the reader html, everything else is replaced by its
+/// children
+const KEEP : &[&str] = &[
+    "a", "b", "blockquote", "br", "code", "dd", "dl", "dt", "em", "figcaption",
+    "figure", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i", "img", "li", "ol",
+    "p", "pre", "strong", "sub", "sup", "table", "tbody", "td", "th", "thead",
+    "tr", "ul",
+];
+
+/// elements whose text is scored
+const PARAGRAPHS : &[&str] = &["blockquote", "p", "pre", "td"];
+
+#[derive(Debug)]
+pub struct Article {
+    pub title : Option<String>,
+    /// a complete html document
+    pub html :  String,
+    pub text :  String,
+    pub words : u32,
+}
+
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+struct Element {
+    name :     String,
+    attrs :    Vec<(String, Option<String>)>,
+    children : Vec<Node>,
+}
+
+impl Element {
+    fn new(name : &str, attrs : Vec<(String, Option<String>)>) -> Self {
+        Self {
+            name : name.to_string(),
+            attrs,
+            children : Vec::new(),
+        }
+    }
+
+    fn attr(&self, name : &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|child| match child {
+            Node::Element(el) => Some(el),
+            Node::Text(_) => None,
+        })
+    }
+
+    fn text_len(&self) -> usize {
+        self.children
+            .iter()
+            .map(|child| match child {
+                Node::Element(el) => el.text_len(),
+                Node::Text(text) => text.trim().chars().count(),
+            })
+            .sum()
+    }
+
+    fn link_text_len(&self) -> usize {
+        if self.name == "a" {
+            return self.text_len()
+        }
+
+        self.elements().map(Element::link_text_len).sum()
+    }
+
+    fn link_density(&self) -> f64 {
+        let len = self.text_len();
+        if len == 0 {
+            return 0.0
+        }
+
+        self.link_text_len() as f64 / len as f64
+    }
+
+    fn commas(&self) -> usize {
+        self.children
+            .iter()
+            .map(|child| match child {
+                Node::Element(el) => el.commas(),
+                Node::Text(text) => text.matches(',').count(),
+            })
+            .sum()
+    }
+
+    fn is_unlikely(&self) -> bool {
+        if REMOVE.contains(&self.name.as_str()) {
+            return true
+        }
+
+        if matches!(self.name.as_str(), "html" | "body" | "article" | "main") {
+            return false
+        }
+
+        let words = [self.attr("class"), self.attr("id")]
+            .iter()
+            .flatten()
+            .flat_map(|v| v.split(|c : char| !c.is_ascii_alphanumeric()))
+            .map(str::to_ascii_lowercase)
+            .collect::<Vec<_>>();
+
+        let has = |list : &[&str]| {
+            words.iter().any(|w| list.contains(&w.as_str()))
+        };
+
+        has(UNLIKELY) && !has(LIKELY)
+    }
+}
+
+/// a forgiving tree builder: unknown end tags are ignored and unclosed
+/// elements are closed by their parent's end tag
+fn parse(html : &str) -> Element {
+    let mut stack = vec![Element::new("#root", Vec::new())];
+
+    fn close(stack : &mut Vec<Element>) {
+        let el = stack.pop().unwrap();
+        stack.last_mut().unwrap().children.push(Node::Element(el));
+    }
+
+    for token in Tokenizer::new(html) {
+        match token {
+            Token::Start(tag) => {
+                // paragraphs and list items are often left open, and
+                // paragraphs can't contain blocks
+                let top = &stack.last().unwrap().name;
+                let implied = matches!(
+                    tag.name.as_str(),
+                    "p" | "li" | "dt" | "dd" | "tr" | "td" | "th"
+                );
+                let block = html::BLOCK.contains(&tag.name.as_str())
+                    && tag.name != "br";
+                if ((implied && *top == tag.name) || (block && top == "p"))
+                    && stack.len() > 1
+                {
+                    close(&mut stack);
+                }
+
+                let el = Element::new(&tag.name, tag.attrs);
+
+                if VOID.contains(&el.name.as_str())
+                    || tag.self_closing
+                    || stack.len() > MAX_DEPTH
+                {
+                    stack.last_mut().unwrap().children.push(Node::Element(el));
+                } else {
+                    stack.push(el);
+                }
+            },
+            Token::End(name) => {
+                // the root is never closed, which shifts the index by one
+                let open = stack.iter().skip(1).rposition(|el| el.name == name);
+                if let Some(i) = open {
+                    while stack.len() > i + 1 {
+                        close(&mut stack);
+                    }
+                }
+            },
+            Token::Text(text) => {
+                stack
+                    .last_mut()
+                    .unwrap()
+                    .children
+                    .push(Node::Text(unescape(text)));
+            },
+            Token::Other(_) => {},
+        }
+    }
+
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+
+    stack.pop().unwrap()
+}
+
+fn remove_unlikely(el : &mut Element) {
+    el.children.retain(|child| match child {
+        Node::Element(child) => !child.is_unlikely(),
+        Node::Text(_) => true,
+    });
+
+    for child in el.children.iter_mut() {
+        if let Node::Element(child) = child {
+            remove_unlikely(child);
+        }
+    }
+}
+
+fn paragraph_score(el : &Element) -> f64 {
+    if !PARAGRAPHS.contains(&el.name.as_str()) {
+        return 0.0
+    }
+
+    let len = el.text_len();
+    if len < 25 {
+        return 0.0
+    }
+
+    1.0 + el.commas() as f64 + (len as f64 / 100.0).min(3.0)
+}
+
+/// how much the element looks like the article, from the paragraphs it
+/// contains directly and those one level down
+fn score(el : &Element) -> f64 {
+    let mut score = 0.0;
+
+    for child in el.elements() {
+        score += paragraph_score(child);
+
+        for grandchild in child.elements() {
+            score += paragraph_score(grandchild) / 2.0;
+        }
+    }
+
+    if score == 0.0 {
+        return 0.0
+    }
+
+    let bonus = match el.name.as_str() {
+        "article" => 10.0,
+        "main" | "div" => 5.0,
+        "section" | "blockquote" | "pre" | "td" => 3.0,
+        "ol" | "ul" | "dl" | "li" => -3.0,
+        _ => 0.0,
+    };
+
+    (score + bonus) * (1.0 - el.link_density())
+}
+
+fn best<'a>(el : &'a Element, found : &mut Option<(f64, &'a Element)>) {
+    let s = score(el);
+    if s > found.map(|(best, _)| best).unwrap_or(0.0) {
+        *found = Some((s, el));
+    }
+
+    for child in el.elements() {
+        best(child, found);
+    }
+}
+
+fn find<'a>(el : &'a Element, name : &str) -> Option<&'a Element> {
+    if el.name == name {
+        return Some(el)
+    }
+
+    el.elements().find_map(|child| find(child, name))
+}
+
+fn to_html(el : &Element, base : &Url, out : &mut String) {
+    for child in el.children.iter() {
+        let child = match child {
+            Node::Text(text) => {
+                out.push_str(&escape(text));
+                continue
+            },
+            Node::Element(child) => child,
+        };
+
+        // leftover lists of links, like tag clouds and "read more" sections
+        let is_list = matches!(child.name.as_str(), "ul" | "ol" | "table");
+        if is_list && child.link_density() > 0.5 {
+            continue
+        }
+
+        if !KEEP.contains(&child.name.as_str()) {
+            to_html(child, base, out);
+            continue
+        }
+
+        let mut tag = html::Tag {
+            name :         child.name.clone(),
+            attrs :        Vec::new(),
+            self_closing : false,
+        };
+
+        let absolute = |attr| {
+            child.attr(attr)
+                .and_then(|v| base.join(v.trim()).ok())
+                .filter(|url| matches!(url.scheme(), "http" | "https" | "data"))
+                .map(String::from)
+        };
+
+        match child.name.as_str() {
+            "a" => {
+                if let Some(href) = absolute("href") {
+                    tag.set_attr("href", href);
+                }
+            },
+            "img" => {
+                let src = match absolute("src") {
+                    Some(src) => src,
+                    None => continue,
+                };
+                tag.set_attr("src", src);
+
+                if let Some(alt) = child.attr("alt") {
+                    tag.set_attr("alt", alt.to_string());
+                }
+            },
+            _ => {},
+        }
+
+        out.push_str(&tag.to_html());
+
+        if !VOID.contains(&child.name.as_str()) {
+            to_html(child, base, out);
+            out.push_str(&format!("</{}>", child.name));
+        }
+    }
+}
+
+/// extracts the article from the page, base is the url the page was fetched
+/// from
+pub fn extract(page : &[u8], base : &Url) -> Article {
+    let title = metadata::extract(page, base).title;
+
+    let mut root = parse(&String::from_utf8_lossy(page));
+    remove_unlikely(&mut root);
+
+    let mut found = None;
+    best(&root, &mut found);
+
+    let article = found
+        .map(|(_, el)| el)
+        .or_else(|| find(&root, "body"))
+        .unwrap_or(&root);
+
+    let mut content = String::new();
+    to_html(article, base, &mut content);
+
+    let text = html::text(&content);
+    let words = text.split_whitespace().count() as u32;
+
+    let title_html = title
+        .as_deref()
+        .map(escape)
+        .unwrap_or_default();
+
+    let html = format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{title}</title>
+<style>
+body {{
+    max-width: 40em;
+    margin: 2em auto;
+    padding: 0 1em;
+    line-height: 1.6;
+    font-family: serif;
+}}
+img {{ max-width: 100%; height: auto; }}
+pre {{ overflow-x: auto; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+{content}
+</body>
+</html>
+",
+        title = title_html,
+        content = content,
+    );
+
+    Article {
+        title,
+        html,
+        text,
+        words,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARAGRAPH : &str = "The quick brown fox jumps over the lazy dog, \
+                              again and again, until the dog gets up.";
+
+    fn base() -> Url {
+        Url::parse("https://ear7h.net/posts/a.html").unwrap()
+    }
+
+    #[test]
+    fn parse_closes_implied_elements() {
+        let root = parse("<div><p>one<p>two<ul><li>a<li>b</ul></div>");
+
+        let div = root.elements().next().unwrap();
+        let names = div
+            .elements()
+            .map(|el| el.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["p", "p", "ul"]);
+
+        let ul = div.elements().nth(2).unwrap();
+        assert_eq!(ul.elements().count(), 2);
+    }
+
+    #[test]
+    fn unlikely_elements() {
+        let el = |name : &str, class : &str| {
+            Element::new(name, vec![("class".to_string(), Some(class.into()))])
+        };
+
+        assert!(el("div", "sidebar").is_unlikely());
+        assert!(el("div", "share-buttons").is_unlikely());
+        assert!(el("nav", "").is_unlikely());
+        assert!(!el("div", "post-comments-count content").is_unlikely());
+        assert!(!el("article", "comments").is_unlikely());
+        assert!(!el("div", "adventure").is_unlikely());
+    }
+
+    #[test]
+    fn extract_article() {
+        let html = format!(
+            r#"<html><head><title>A &amp; B</title>
+            <script>var x = "<p>no</p>";</script></head>
+            <body>
+            <nav><a href="/">home</a> <a href="/about">about</a></nav>
+            <div class="sidebar"><p>{p}</p></div>
+            <div class="post">
+                <p>{p}</p>
+                <p>{p} <a href="b.html">more</a></p>
+                <img src="img.png" alt="a picture" onload="f()">
+                <img alt="no source">
+                <ul><li><a href="/1">1</a><li><a href="/2">2</a></ul>
+            </div>
+            <div class="comments"><p>{p}</p></div>
+            </body></html>"#,
+            p = PARAGRAPH,
+        );
+
+        let article = extract(html.as_bytes(), &base());
+        assert_eq!(article.title.as_deref(), Some("A & B"));
+        assert_eq!(
+            article.words,
+            2 * PARAGRAPH.split_whitespace().count() as u32 + 1,
+        );
+
+        let content = article
+            .html
+            .split("<h1>A &amp; B</h1>")
+            .nth(1)
+            .unwrap();
+        assert_eq!(
+            content.split_whitespace().collect::<Vec<_>>().join(" "),
+            format!(
+                "<p>{p}</p> <p>{p} <a \
+                 href=\"https://ear7h.net/posts/b.html\">more</a></p> <img \
+                 src=\"https://ear7h.net/posts/img.png\" alt=\"a picture\"> \
+                 </body> </html>",
+                p = PARAGRAPH,
+            ),
+        );
+    }
+
+    #[test]
+    fn extract_falls_back_to_body() {
+        let article = extract(b"<body><span>short</span></body>", &base());
+
+        assert_eq!(article.title, None);
+        assert_eq!(article.text.trim(), "short");
+        assert_eq!(article.words, 1);
+    }
+}
@@ -3,12 +3,26 @@ use serde::{Serialize, Serializer};
 #[derive(Debug)]
 pub struct Time(time::OffsetDateTime);
 
-pub(crate) const TIME_FORMAT : &'static [time::format_description::FormatItem<
+pub(crate) const TIME_FORMAT : &[time::format_description::FormatItem<
     'static,
 >] = time::macros::format_description!(
     "[year]-[month]-[day] [hour]:[minute]:[second]"
 );
 
+/// rfc 3339 in utc, as used by atom
+pub(crate) const RFC3339_FORMAT : &[time::format_description::FormatItem<
+    'static,
+>] = time::macros::format_description!(
+    "[year]-[month]-[day]T[hour]:[minute]:[second]Z"
+);
+
+/// rfc 822 dates in gmt, as used by rss and http headers
+pub(crate) const HTTP_DATE_FORMAT : &[time::format_description::FormatItem<
+    'static,
+>] = time::macros::format_description!(
+    "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT"
+);
+
 impl Serialize for Time {
     fn serialize<S>(
         &self,
@@ -5,6 +5,49 @@ use super::*;
 
 pub struct Renderer(Handlebars<'static>);
 
+/// where render puts the csrf token in the root of a template's data
+const CSRF_KEY : &str = "_csrf";
+
+fn reading_minutes(
+    h : &handlebars::Helper,
+    _ : &Handlebars,
+    _ : &handlebars::Context,
+    _ : &mut handlebars::RenderContext,
+    out : &mut dyn handlebars::Output,
+) -> handlebars::HelperResult {
+    let words = h.param(0)
+        .and_then(|v| v.value().as_u64())
+        .ok_or_else(|| {
+            handlebars::RenderError::new("reading_minutes: expected a word count")
+        })?;
+
+    out.write(&models::reading_minutes(words as u32).to_string())?;
+    Ok(())
+}
+
+/// the hidden input with the csrf token, every form which posts needs one
+fn csrf_field(
+    _ : &handlebars::Helper,
+    _ : &Handlebars,
+    ctx : &handlebars::Context,
+    _ : &mut handlebars::RenderContext,
+    out : &mut dyn handlebars::Output,
+) -> handlebars::HelperResult {
+    let csrf = ctx.data()
+        .get(CSRF_KEY)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| {
+            handlebars::RenderError::new("csrf_field: no csrf token to render")
+        })?;
+
+    out.write(&format!(
+        "<input type=\"hidden\" name=\"{}\" value=\"{}\">",
+        csrf::FIELD,
+        html::escape(csrf),
+    ))?;
+    Ok(())
+}
+
 impl Renderer {
     pub fn new() -> Self {
         let mut t = Handlebars::new();
@@ -31,35 +74,442 @@ impl Renderer {
             };
         }
 
+        t.register_helper("reading_minutes", Box::new(reading_minutes));
+        t.register_helper("csrf_field", Box::new(csrf_field));
+
         register! {
             ("users-links", "../ui/users-links.html")
+            ("users-trash", "../ui/users-trash.html")
+            ("users-link-edit", "../ui/users-link-edit.html")
+            ("users-tokens", "../ui/users-tokens.html")
+            ("users-search", "../ui/users-search.html")
+            ("users-import", "../ui/users-import.html")
+            ("users-link-history", "../ui/users-link-history.html")
+            ("users-link-diff", "../ui/users-link-diff.html")
+            ("users-collections", "../ui/users-collections.html")
+            ("users-public", "../ui/users-public.html")
+            ("users-workspaces", "../ui/users-workspaces.html")
+            ("users-members", "../ui/users-members.html")
+            ("admin-users", "../ui/admin-users.html")
+            ("login", "../ui/login.html")
         }
 
         Self(t)
     }
 
+    /// collection is set when the links are the ones in it. editor is false
+    /// for people other than the owner, who can only read.
     pub fn users_links(
         &self,
         user : &models::User,
-        links : &[models::Link],
+        page : &models::LinksPage,
+        collection : Option<&models::Collection>,
+        tags : &[models::Tag],
         editor : bool,
+        csrf : &str,
     ) -> String {
         #[derive(Serialize)]
         struct Ctx<'a> {
-            user :   &'a models::User,
-            links :  &'a [models::Link],
+            user :       &'a models::User,
+            links :      &'a [models::Link],
+            collection : Option<&'a models::Collection>,
+            tags :       &'a [models::Tag],
+            sort :   models::Sort,
+            health : Option<models::Health>,
+            /// query string for the next page
+            next :   Option<String>,
             editor : bool,
         }
 
-        self.0.render("users-links", &Ctx {
+        #[derive(Serialize)]
+        struct NextQuery<'a> {
+            sort :   models::Sort,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            health : Option<models::Health>,
+            cursor : &'a str,
+        }
+
+        let next = page.next.as_ref().map(|cursor| {
+            serde_urlencoded::to_string(NextQuery {
+                sort : page.sort,
+                health : page.health,
+                cursor,
+            })
+            .unwrap()
+        });
+
+        self.render("users-links", &Ctx {
             user,
-            links,
+            links : &page.links,
+            collection,
+            tags,
+            sort : page.sort,
+            health : page.health,
+            next,
             editor,
+        }, csrf)
+    }
+
+    pub fn users_trash(
+        &self,
+        user : &models::User,
+        links : &[models::Link],
+        csrf : &str,
+    ) -> String {
+        #[derive(Serialize)]
+        struct Ctx<'a> {
+            user :  &'a models::User,
+            links : &'a [models::Link],
+        }
+
+        self.render("users-trash", &Ctx {
+            user,
+            links,
+        }, csrf)
+    }
+
+    /// in_collections is the ids of the collections the link is in
+    pub fn users_link_edit(
+        &self,
+        user : &models::User,
+        link : &models::Link,
+        collections : &[models::Collection],
+        in_collections : &[u32],
+        csrf : &str,
+    ) -> String {
+        #[derive(Serialize)]
+        struct Row<'a> {
+            collection : &'a models::Collection,
+            member :     bool,
+        }
+
+        #[derive(Serialize)]
+        struct Ctx<'a> {
+            user :        &'a models::User,
+            link :        &'a models::Link,
+            collections : Vec<Row<'a>>,
+        }
+
+        let collections = collections
+            .iter()
+            .map(|collection| Row {
+                collection,
+                member : in_collections.contains(&collection.id),
+            })
+            .collect();
+
+        self.render("users-link-edit", &Ctx {
+            user,
+            link,
+            collections,
+        }, csrf)
+    }
+
+    pub fn users_collections(
+        &self,
+        user : &models::User,
+        collections : &[models::Collection],
+        csrf : &str,
+    ) -> String {
+        #[derive(Serialize)]
+        struct Ctx<'a> {
+            user :        &'a models::User,
+            collections : &'a [models::Collection],
+        }
+
+        self.render("users-collections", &Ctx {
+            user,
+            collections,
+        }, csrf)
+    }
+
+    /// the page anyone can see, listing the user's public collections
+    pub fn users_public(
+        &self,
+        user : &models::User,
+        collections : &[models::Collection],
+    ) -> String {
+        #[derive(Serialize)]
+        struct Ctx<'a> {
+            user :        &'a models::User,
+            collections : &'a [models::Collection],
+        }
+
+        self.0.render("users-public", &Ctx {
+            user,
+            collections,
         })
         .unwrap()
     }
 
-    pub fn login(&self) -> &'static str {
-        include_str!("../ui/login.html")
+    pub fn users_link_history(
+        &self,
+        user : &models::User,
+        link : &models::Link,
+        snapshots : &[models::Snapshot],
+        csrf : &str,
+    ) -> String {
+        #[derive(Serialize)]
+        struct Ctx<'a> {
+            user :      &'a models::User,
+            link :      &'a models::Link,
+            snapshots : &'a [models::Snapshot],
+        }
+
+        self.render("users-link-history", &Ctx {
+            user,
+            link,
+            snapshots,
+        }, csrf)
+    }
+
+    /// changes is none when either snapshot isn't text
+    pub fn users_link_diff(
+        &self,
+        user : &models::User,
+        link : &models::Link,
+        from : &models::Snapshot,
+        to : &models::Snapshot,
+        changes : Option<&[diff::Change]>,
+    ) -> String {
+        #[derive(Serialize)]
+        struct Ctx<'a> {
+            user :    &'a models::User,
+            link :    &'a models::Link,
+            from :    &'a models::Snapshot,
+            to :      &'a models::Snapshot,
+            changes : &'a [diff::Change<'a>],
+            /// whether both snapshots are text
+            text :    bool,
+            /// whether anything changed at all
+            changed : bool,
+        }
+
+        let changed = changes
+            .unwrap_or_default()
+            .iter()
+            .any(|c| !matches!(c, diff::Change::Same(_)));
+
+        self.0.render("users-link-diff", &Ctx {
+            user,
+            link,
+            from,
+            to,
+            changes : changes.unwrap_or_default(),
+            text : changes.is_some(),
+            changed,
+        })
+        .unwrap()
+    }
+
+    /// new_token is shown once, right after it's created
+    pub fn users_tokens(
+        &self,
+        user : &models::User,
+        tokens : &[models::ApiToken],
+        new_token : Option<&str>,
+        feed_token : &str,
+        csrf : &str,
+    ) -> String {
+        #[derive(Serialize)]
+        struct Ctx<'a> {
+            user :       &'a models::User,
+            tokens :     &'a [models::ApiToken],
+            new_token :  Option<&'a str>,
+            feed_token : &'a str,
+        }
+
+        self.render("users-tokens", &Ctx {
+            user,
+            tokens,
+            new_token,
+            feed_token,
+        }, csrf)
+    }
+
+    pub fn users_search(
+        &self,
+        user : &models::User,
+        query : &str,
+        results : &[models::SearchResult],
+    ) -> String {
+        #[derive(Serialize)]
+        struct Row<'a> {
+            link :    &'a models::Link,
+            snippet : String,
+        }
+
+        #[derive(Serialize)]
+        struct Ctx<'a> {
+            user :    &'a models::User,
+            query :   &'a str,
+            results : Vec<Row<'a>>,
+        }
+
+        // the snippet is rendered unescaped so the highlights show up
+        let results = results
+            .iter()
+            .map(|res| Row {
+                link :    &res.link,
+                snippet : handlebars::html_escape(&res.snippet)
+                    .replace('\u{2}', "<mark>")
+                    .replace('\u{3}', "</mark>"),
+            })
+            .collect();
+
+        self.0.render("users-search", &Ctx {
+            user,
+            query,
+            results,
+        })
+        .unwrap()
+    }
+
+    /// report is the outcome of an import that just happened, if any
+    pub fn users_import(
+        &self,
+        user : &models::User,
+        report : Option<&bookmarks::ImportReport>,
+        csrf : &str,
+    ) -> String {
+        #[derive(Serialize)]
+        struct Ctx<'a> {
+            user :   &'a models::User,
+            report : Option<&'a bookmarks::ImportReport>,
+        }
+
+        self.render("users-import", &Ctx {
+            user,
+            report,
+        }, csrf)
+    }
+
+    pub fn users_workspaces(
+        &self,
+        user : &models::User,
+        workspaces : &[models::Workspace],
+        csrf : &str,
+    ) -> String {
+        #[derive(Serialize)]
+        struct Ctx<'a> {
+            user :       &'a models::User,
+            workspaces : &'a [models::Workspace],
+        }
+
+        self.render("users-workspaces", &Ctx {
+            user,
+            workspaces,
+        }, csrf)
+    }
+
+    /// user is the one looking at the page, workspace has their role
+    pub fn users_members(
+        &self,
+        user : &models::User,
+        workspace : &models::Workspace,
+        members : &[models::Member],
+        csrf : &str,
+    ) -> String {
+        #[derive(Serialize)]
+        struct Ctx<'a> {
+            user :      &'a models::User,
+            workspace : &'a models::Workspace,
+            members :   &'a [models::Member],
+            owner :     bool,
+        }
+
+        self.render("users-members", &Ctx {
+            user,
+            workspace,
+            members,
+            owner : workspace.role == models::Role::Owner,
+        }, csrf)
+    }
+
+    /// user is the admin looking at the page
+    pub fn admin_users(
+        &self,
+        user : &models::User,
+        users : &[models::UserSummary],
+        csrf : &str,
+    ) -> String {
+        #[derive(Serialize)]
+        struct Ctx<'a> {
+            user :  &'a models::User,
+            users : &'a [models::UserSummary],
+        }
+
+        self.render("admin-users", &Ctx {
+            user,
+            users,
+        }, csrf)
+    }
+
+    pub fn login(&self, csrf : &str) -> String {
+        self.render("login", &serde_json::json!({}), csrf)
+    }
+
+    /// renders the template with the csrf token for its forms, which put it
+    /// in with the csrf_field helper
+    fn render<T : Serialize>(
+        &self,
+        name : &str,
+        ctx : &T,
+        csrf : &str,
+    ) -> String {
+        let mut data = serde_json::to_value(ctx).unwrap();
+        data[CSRF_KEY] = csrf.into();
+
+        self.0.render(name, &data).unwrap()
+    }
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csrf_field_escapes_token() {
+        let mut t = Handlebars::new();
+        t.set_strict_mode(true);
+        t.register_helper("csrf_field", Box::new(csrf_field));
+
+        let page = t.render_template(
+            "{{#each links}}{{ csrf_field }}{{/each}}",
+            &serde_json::json!({ "links" : [1], CSRF_KEY : "a\"b" }),
+        ).unwrap();
+        assert_eq!(
+            page,
+            "<input type=\"hidden\" name=\"csrf\" value=\"a&quot;b\">",
+        );
+
+        assert!(t.render_template("{{ csrf_field }}", &()).is_err());
+    }
+
+    #[test]
+    fn post_forms_have_csrf_field() {
+        for entry in std::fs::read_dir("ui").unwrap() {
+            let path = entry.unwrap().path();
+            let page = std::fs::read_to_string(&path).unwrap();
+            let mut lines = page.lines();
+
+            while let Some(line) = lines.next() {
+                if line.contains("<form") && line.contains("method=\"post\"") {
+                    assert_eq!(
+                        lines.next().map(str::trim),
+                        Some("{{ csrf_field }}"),
+                        "{}: {}",
+                        path.display(),
+                        line.trim(),
+                    );
+                }
+            }
+        }
     }
 }
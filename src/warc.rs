@@ -0,0 +1,296 @@
+//! WARC 1.1 records for archived snapshots, so they can be replayed with
+//! standard web archive tooling. each fetch becomes a request and a response
+//! record, and every record is its own gzip member as is usual for .warc.gz
+//! files.
+
+use std::io::Write;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::{archiver, hashing, models};
+
+pub const CONTENT_TYPE : &str = "application/gzip";
+pub const FILE_NAME : &str = "links.warc.gz";
+
+const WARC_DATE : &[time::format_description::FormatItem<'static>] =
+    time::macros::format_description!(
+        "[year]-[month]-[day]T[hour]:[minute]:[second]Z"
+    );
+
+/// response headers which describe how the body was sent rather than the
+/// body we stored, content-length is recomputed
+const HOP_HEADERS : &[&str] = &["transfer-encoding", "content-length"];
+
+fn warc_date(t : &time::OffsetDateTime) -> String {
+    t.to_offset(time::UtcOffset::UTC).format(&WARC_DATE).unwrap()
+}
+
+/// a stable record id, so exporting the same snapshot twice gives the same
+/// records
+fn record_id(kind : &str, id : u32, date : &time::OffsetDateTime) -> String {
+    let seed = format!("link-archive:{}:{}:{}", kind, id, warc_date(date));
+    let mut b = hashing::sha256(seed.as_bytes());
+
+    // a version 8 (custom) uuid
+    b[6] = (b[6] & 0x0f) | 0x80;
+    b[8] = (b[8] & 0x3f) | 0x80;
+
+    let hex = hashing::hex(&b[..16]);
+    format!(
+        "<urn:uuid:{}-{}-{}-{}-{}>",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..],
+    )
+}
+
+fn record(headers : &[(&str, String)], block : &[u8]) -> Vec<u8> {
+    let mut out = b"WARC/1.1\r\n".to_vec();
+
+    for (name, value) in headers {
+        out.extend_from_slice(format!("{}: {}\r\n", name, value).as_bytes());
+    }
+
+    out.extend_from_slice(
+        format!(
+            "WARC-Block-Digest: sha256:{}\r\n",
+            hashing::sha256_hex(block),
+        )
+        .as_bytes(),
+    );
+    out.extend_from_slice(
+        format!("Content-Length: {}\r\n\r\n", block.len()).as_bytes(),
+    );
+    out.extend_from_slice(block);
+    out.extend_from_slice(b"\r\n\r\n");
+
+    gzip(&out)
+}
+
+fn gzip(data : &[u8]) -> Vec<u8> {
+    let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+    enc.write_all(data).unwrap();
+    enc.finish().unwrap()
+}
+
+/// the warcinfo record which starts the file
+pub fn warcinfo(date : &time::OffsetDateTime) -> Vec<u8> {
+    let fields = format!(
+        "software: link-archive/{}\r\nformat: WARC File Format 1.1\r\n",
+        env!("CARGO_PKG_VERSION"),
+    );
+
+    record(
+        &[
+            ("WARC-Type", "warcinfo".to_string()),
+            ("WARC-Record-ID", record_id("warcinfo", 0, date)),
+            ("WARC-Date", warc_date(date)),
+            ("WARC-Filename", FILE_NAME.to_string()),
+            ("Content-Type", "application/warc-fields".to_string()),
+        ],
+        fields.as_bytes(),
+    )
+}
+
+/// the request and response records of a successful fetch, body is the blob
+/// of the snapshot's digest. warcinfo_date is the date given to warcinfo.
+pub fn snapshot(
+    warcinfo_date : &time::OffsetDateTime,
+    snapshot : &models::Snapshot,
+    body : &[u8],
+) -> Vec<u8> {
+    let url = match url::Url::parse(&snapshot.url) {
+        Ok(url) => url,
+        Err(_) => return Vec::new(),
+    };
+
+    let warcinfo_id = record_id("warcinfo", 0, warcinfo_date);
+    let request_id = record_id("request", snapshot.id, &snapshot.fetched);
+    let response_id = record_id("response", snapshot.id, &snapshot.fetched);
+    let date = warc_date(&snapshot.fetched);
+
+    // the archiver only sends these, and hyper adds the host
+    let mut path = url.path().to_string();
+    if let Some(query) = url.query() {
+        path.push('?');
+        path.push_str(query);
+    }
+    let request = format!(
+        "GET {} HTTP/1.1\r\nhost: {}\r\nuser-agent: {}\r\n\r\n",
+        path,
+        &url[url::Position::BeforeHost..url::Position::AfterPort],
+        archiver::USER_AGENT,
+    );
+
+    let status = snapshot.status.unwrap_or(200);
+    let reason = http::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|s| s.canonical_reason())
+        .unwrap_or("");
+
+    let headers : Vec<(String, String)> =
+        serde_json::from_str(&snapshot.headers).unwrap_or_default();
+
+    let mut response = format!("HTTP/1.1 {} {}\r\n", status, reason);
+    for (name, value) in headers.iter() {
+        if !HOP_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+            response += &format!("{}: {}\r\n", name, value);
+        }
+    }
+    response += &format!("content-length: {}\r\n\r\n", body.len());
+
+    let mut response = response.into_bytes();
+    response.extend_from_slice(body);
+
+    let payload_digest = format!("sha256:{}", hashing::sha256_hex(body));
+
+    let mut out = record(
+        &[
+            ("WARC-Type", "request".to_string()),
+            ("WARC-Record-ID", request_id),
+            ("WARC-Date", date.clone()),
+            ("WARC-Target-URI", url.to_string()),
+            ("WARC-Warcinfo-ID", warcinfo_id.clone()),
+            ("WARC-Concurrent-To", response_id.clone()),
+            ("Content-Type", "application/http;msgtype=request".to_string()),
+        ],
+        request.as_bytes(),
+    );
+
+    out.extend(record(
+        &[
+            ("WARC-Type", "response".to_string()),
+            ("WARC-Record-ID", response_id),
+            ("WARC-Date", date),
+            ("WARC-Target-URI", url.to_string()),
+            ("WARC-Warcinfo-ID", warcinfo_id),
+            ("WARC-Payload-Digest", payload_digest),
+            ("Content-Type", "application/http;msgtype=response".to_string()),
+        ],
+        &response,
+    ));
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::MultiGzDecoder;
+
+    use super::*;
+
+    fn date() -> time::OffsetDateTime {
+        time::macros::datetime!(2021-09-30 12:34:56 -7)
+    }
+
+    fn gunzip(data : &[u8]) -> String {
+        let mut out = String::new();
+        MultiGzDecoder::new(data).read_to_string(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn record_ids_are_stable_uuids() {
+        let id = record_id("response", 1, &date());
+
+        assert_eq!(id, record_id("response", 1, &date()));
+        assert_ne!(id, record_id("request", 1, &date()));
+        assert_ne!(id, record_id("response", 2, &date()));
+
+        let uuid = id
+            .strip_prefix("<urn:uuid:")
+            .and_then(|s| s.strip_suffix('>'))
+            .unwrap();
+        let groups = uuid.split('-').map(str::len).collect::<Vec<_>>();
+        assert_eq!(groups, vec![8, 4, 4, 4, 12]);
+        assert_eq!(&uuid[14..15], "8");
+    }
+
+    #[test]
+    fn warcinfo_record() {
+        let out = gunzip(&warcinfo(&date()));
+
+        assert!(out.starts_with("WARC/1.1\r\nWARC-Type: warcinfo\r\n"));
+        assert!(out.contains("\r\nWARC-Date: 2021-09-30T19:34:56Z\r\n"));
+        assert!(out.contains("\r\nWARC-Filename: links.warc.gz\r\n"));
+        assert!(out.ends_with("format: WARC File Format 1.1\r\n\r\n\r\n"));
+    }
+
+    #[test]
+    fn snapshot_records() {
+        let snap = models::Snapshot {
+            id :             7,
+            link_id :        1,
+            url :            "https://ear7h.net:8443/a?b=c".to_string(),
+            fetched :        date().into(),
+            status :         Some(404),
+            headers :        r#"[["Content-Type", "text/html"],
+                ["Transfer-Encoding", "chunked"],
+                ["content-length", "1000"]]"#
+                .to_string(),
+            content_type :   Some("text/html".to_string()),
+            digest :         None,
+            inlined_digest : None,
+            reader_digest :  None,
+            text_digest :    None,
+            words :          None,
+            error :          None,
+        };
+
+        let out = gunzip(&snapshot(&date(), &snap, b"gone"));
+        // the response record follows the request record
+        let (request, response) = out.split_at(out.rfind("WARC/1.1").unwrap());
+
+        assert!(request.contains("\r\nWARC-Type: request\r\n"));
+        assert!(request.contains(concat!(
+            "\r\n\r\nGET /a?b=c HTTP/1.1\r\n",
+            "host: ear7h.net:8443\r\n",
+        )));
+        assert!(request.contains(&format!(
+            "\r\nWARC-Concurrent-To: {}\r\n",
+            record_id("response", 7, &date()),
+        )));
+
+        assert!(response.contains("\r\nWARC-Type: response\r\n"));
+        assert!(response.contains(
+            "\r\nWARC-Target-URI: https://ear7h.net:8443/a?b=c\r\n"
+        ));
+        assert!(response.contains(&format!(
+            "\r\nWARC-Payload-Digest: sha256:{}\r\n",
+            hashing::sha256_hex(b"gone"),
+        )));
+        assert!(response.ends_with(concat!(
+            "\r\n\r\nHTTP/1.1 404 Not Found\r\n",
+            "Content-Type: text/html\r\n",
+            "content-length: 4\r\n",
+            "\r\n",
+            "gone\r\n\r\n",
+        )));
+    }
+
+    #[test]
+    fn snapshot_invalid_url() {
+        let snap = models::Snapshot {
+            id :             7,
+            link_id :        1,
+            url :            "not a url".to_string(),
+            fetched :        date().into(),
+            status :         None,
+            headers :        "[]".to_string(),
+            content_type :   None,
+            digest :         None,
+            inlined_digest : None,
+            reader_digest :  None,
+            text_digest :    None,
+            words :          None,
+            error :          None,
+        };
+
+        assert!(snapshot(&date(), &snap, b"").is_empty());
+    }
+}